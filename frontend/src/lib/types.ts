@@ -10,6 +10,7 @@ export interface GameMetadata {
   rating?: string;
   image_path?: string;
   error_message?: string;
+  confidence?: number;
 }
 
 export interface GameGuides {
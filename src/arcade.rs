@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// What kind of romset an arcade set is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SetKind {
+    #[default]
+    Game,
+    /// System BIOS shared by other sets, e.g. `neogeo`
+    Bios,
+    /// Device ROMs referenced by other sets, e.g. `qsound`
+    Device,
+    /// Pinball and other electromechanical machines
+    Mechanical,
+}
+
+impl fmt::Display for SetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetKind::Game => write!(f, "game"),
+            SetKind::Bios => write!(f, "BIOS"),
+            SetKind::Device => write!(f, "device"),
+            SetKind::Mechanical => write!(f, "mechanical"),
+        }
+    }
+}
+
+/// An arcade romset from a MAME or FBNeo list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArcadeSet {
+    /// Short romset name, e.g. "sf2"
+    pub name: String,
+    /// Full title, e.g. "Street Fighter II: The World Warrior (World 910522)"
+    pub description: String,
+    pub year: Option<String>,
+    pub manufacturer: Option<String>,
+    /// Parent set, for clones
+    pub clone_of: Option<String>,
+    pub kind: SetKind,
+}
+
+impl ArcadeSet {
+    pub fn is_clone(&self) -> bool {
+        self.clone_of.is_some()
+    }
+
+    /// Check whether the set is a game rather than a BIOS, device or mechanical set
+    pub fn is_playable(&self) -> bool {
+        self.kind == SetKind::Game
+    }
+}
+
+/// MAME `-listxml` output (`<mame>`) or a Logiqx DAT (`<datafile>`)
+#[derive(Debug, Deserialize)]
+struct MachineList {
+    #[serde(default)]
+    machine: Vec<Machine>,
+    #[serde(default)]
+    game: Vec<Machine>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Machine {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@cloneof")]
+    clone_of: Option<String>,
+    #[serde(rename = "@isbios")]
+    is_bios: Option<String>,
+    #[serde(rename = "@isdevice")]
+    is_device: Option<String>,
+    #[serde(rename = "@ismechanical")]
+    is_mechanical: Option<String>,
+    description: Option<String>,
+    year: Option<String>,
+    manufacturer: Option<String>,
+}
+
+impl From<Machine> for ArcadeSet {
+    fn from(machine: Machine) -> Self {
+        let kind = if machine.is_bios.as_deref() == Some("yes") {
+            SetKind::Bios
+        } else if machine.is_device.as_deref() == Some("yes") {
+            SetKind::Device
+        } else if machine.is_mechanical.as_deref() == Some("yes") {
+            SetKind::Mechanical
+        } else {
+            SetKind::Game
+        };
+
+        Self {
+            description: machine.description.unwrap_or_else(|| machine.name.clone()),
+            name: machine.name,
+            year: machine.year,
+            manufacturer: machine.manufacturer,
+            clone_of: machine.clone_of,
+            kind,
+        }
+    }
+}
+
+/// Parse the sets in a MAME/FBNeo `-listxml` output or Logiqx DAT
+pub fn parse_machine_list(reader: impl BufRead) -> Result<Vec<ArcadeSet>, quick_xml::DeError> {
+    let list: MachineList = quick_xml::de::from_reader(reader)?;
+    Ok(list
+        .machine
+        .into_iter()
+        .chain(list.game)
+        .map(ArcadeSet::from)
+        .collect())
+}
+
+/// Arcade romset names imported from MAME or FBNeo lists, stored in `.collie/arcade/`
+#[derive(Debug, Default)]
+pub struct ArcadeIndex {
+    sets: HashMap<String, ArcadeSet>,
+}
+
+impl ArcadeIndex {
+    /// Get the folder imported arcade lists are stored in
+    pub fn arcade_dir(roms_path: &Path) -> PathBuf {
+        roms_path.join(".collie").join("arcade")
+    }
+
+    /// Import a MAME/FBNeo `-listxml` output or DAT file, replacing any earlier import of
+    /// the same file.
+    ///
+    /// Returns the number of sets imported.
+    pub fn import(roms_path: &Path, list_path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+        let sets = parse_machine_list(BufReader::new(File::open(list_path)?))?;
+
+        let arcade_dir = Self::arcade_dir(roms_path);
+        std::fs::create_dir_all(&arcade_dir)?;
+
+        let stem = list_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("arcade");
+        let index_file = arcade_dir.join(format!(
+            "{}.jsonl",
+            stem.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+        ));
+        let mut file = File::create(&index_file)?;
+        for set in &sets {
+            writeln!(file, "{}", serde_json::to_string(set)?)?;
+        }
+
+        Ok(sets.len())
+    }
+
+    /// Load every imported arcade list
+    pub fn load(roms_path: &Path) -> Self {
+        let mut index = Self::default();
+
+        let Ok(dir) = std::fs::read_dir(Self::arcade_dir(roms_path)) else {
+            return index;
+        };
+        for path in dir.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("Failed to read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                match serde_json::from_str::<ArcadeSet>(&line) {
+                    Ok(set) => {
+                        index.sets.entry(set.name.to_lowercase()).or_insert(set);
+                    }
+                    Err(e) => debug!("Skipping invalid arcade index line: {}", e),
+                }
+            }
+        }
+
+        index
+    }
+
+    /// Number of sets across all imported lists
+    pub fn len(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// Check whether no arcade list has been imported
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    /// Look up a set by its short name, e.g. "mslug"
+    pub fn get(&self, name: &str) -> Option<&ArcadeSet> {
+        self.sets.get(&name.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTXML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE mame [
+<!ELEMENT mame (machine+)>
+]>
+<mame build="0.261" debug="no" mameconfig="10">
+	<machine name="neogeo" sourcefile="neogeo/neogeo.cpp" isbios="yes">
+		<description>Neo-Geo MV-6F</description>
+		<year>1990</year>
+		<manufacturer>SNK</manufacturer>
+		<rom name="sp-s2.sp1" size="131072" crc="9036d879"/>
+	</machine>
+	<machine name="mslug" sourcefile="neogeo/neogeo.cpp" romof="neogeo">
+		<description>Metal Slug - Super Vehicle-001</description>
+		<year>1996</year>
+		<manufacturer>Nazca</manufacturer>
+		<rom name="201-p1.p1" size="2097152" crc="08d8daa5"/>
+		<driver status="good"/>
+	</machine>
+	<machine name="sf2ua" sourcefile="capcom/cps1.cpp" cloneof="sf2" romof="sf2">
+		<description>Street Fighter II: The World Warrior (USA 910206)</description>
+		<year>1991</year>
+		<manufacturer>Capcom</manufacturer>
+	</machine>
+	<machine name="qsound" sourcefile="sound/qsound.cpp" isdevice="yes" runnable="no">
+		<description>Q-Sound</description>
+	</machine>
+</mame>
+"#;
+
+    #[test]
+    fn test_parse_listxml() {
+        let sets = parse_machine_list(LISTXML.as_bytes()).unwrap();
+        assert_eq!(sets.len(), 4);
+
+        assert_eq!(sets[0].kind, SetKind::Bios);
+        assert_eq!(sets[1].description, "Metal Slug - Super Vehicle-001");
+        assert_eq!(sets[1].manufacturer.as_deref(), Some("Nazca"));
+        assert!(sets[1].is_playable());
+        assert!(!sets[1].is_clone());
+        assert_eq!(sets[2].clone_of.as_deref(), Some("sf2"));
+        assert_eq!(sets[3].kind, SetKind::Device);
+    }
+
+    #[test]
+    fn test_parse_logiqx_dat() {
+        let dat = r#"<?xml version="1.0"?>
+<datafile>
+	<header><name>FBNeo - Arcade Games</name></header>
+	<game name="sf2" isbios="no">
+		<description>Street Fighter II - The World Warrior (World 910522)</description>
+		<year>1991</year>
+		<manufacturer>Capcom</manufacturer>
+	</game>
+</datafile>
+"#;
+        let sets = parse_machine_list(dat.as_bytes()).unwrap();
+        assert_eq!(sets.len(), 1);
+        assert_eq!(sets[0].name, "sf2");
+        assert_eq!(sets[0].year.as_deref(), Some("1991"));
+        assert!(sets[0].is_playable());
+    }
+
+    #[test]
+    fn test_import_and_load() {
+        let temp_dir = std::env::temp_dir().join("collie_test_arcade_import");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let list_path = temp_dir.join("mame.xml");
+        std::fs::write(&list_path, LISTXML).unwrap();
+        assert_eq!(ArcadeIndex::import(&temp_dir, &list_path).unwrap(), 4);
+
+        let index = ArcadeIndex::load(&temp_dir);
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.get("MSLUG").unwrap().year.as_deref(), Some("1996"));
+        assert_eq!(index.get("sf2"), None);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
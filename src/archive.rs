@@ -0,0 +1,265 @@
+use std::fs::File;
+use std::path::Path;
+
+use crate::console::Console;
+
+/// Extensions of archives whose contents can be inspected
+const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "7z"];
+
+/// A file stored inside a .zip or .7z archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub size: u64,
+    pub crc: u32,
+}
+
+impl ArchiveEntry {
+    /// Get the file name of the entry without any folders inside the archive
+    pub fn file_name(&self) -> &str {
+        self.name.rsplit(['/', '\\']).next().unwrap_or(&self.name)
+    }
+}
+
+/// Check whether a file is an archive collie can look inside
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| ARCHIVE_EXTENSIONS.iter().any(|a| e.eq_ignore_ascii_case(a)))
+        .unwrap_or(false)
+}
+
+/// Check whether a console's ROMs should be identified by the file inside an archive.
+///
+/// Arcade romsets and consoles without an extension list are identified by the archive
+/// itself, since their archives hold several chip dumps rather than a single ROM.
+pub fn holds_single_rom(path: &Path, console: &Console) -> bool {
+    is_archive(path) && !console.arcade && !console.extensions.is_empty()
+}
+
+/// List the files stored in a .zip or .7z archive.
+///
+/// Sizes and CRCs are read from the archive's directory, so nothing is decompressed.
+pub fn list_entries(path: &Path) -> Result<Vec<ArchiveEntry>, std::io::Error> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    match extension.as_deref() {
+        Some("zip") => {
+            let mut archive =
+                zip::ZipArchive::new(File::open(path)?).map_err(std::io::Error::other)?;
+            let mut entries = Vec::new();
+            for index in 0..archive.len() {
+                let file = archive.by_index_raw(index).map_err(std::io::Error::other)?;
+                if file.is_dir() {
+                    continue;
+                }
+                entries.push(ArchiveEntry {
+                    name: file.name().to_string(),
+                    size: file.size(),
+                    crc: file.crc32(),
+                });
+            }
+            Ok(entries)
+        }
+        Some("7z") => {
+            let archive = sevenz_rust::Archive::open(path).map_err(std::io::Error::other)?;
+            Ok(archive
+                .files
+                .into_iter()
+                .filter_map(seven_zip_entry)
+                .collect())
+        }
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Not an archive: {}", path.display()),
+        )),
+    }
+}
+
+/// Convert a file listed in a .7z archive, skipping folders, empty files and files stored
+/// without a CRC, which could not be matched
+fn seven_zip_entry(file: sevenz_rust::SevenZArchiveEntry) -> Option<ArchiveEntry> {
+    if !file.has_stream || file.is_directory || !file.has_crc {
+        return None;
+    }
+    Some(ArchiveEntry {
+        name: file.name,
+        size: file.size,
+        crc: file.crc as u32,
+    })
+}
+
+/// Pick the ROM inside an archive using the console's extension list.
+///
+/// Entries with one of the console's own extensions win over generic ones; ties go to
+/// the largest entry. Returns `None` when no entry looks like a ROM for the console.
+pub fn find_rom_entry(
+    path: &Path,
+    console: &Console,
+) -> Result<Option<ArchiveEntry>, std::io::Error> {
+    let entries = list_entries(path)?;
+
+    let rom = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let extension = Path::new(entry.file_name())
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_lowercase())?;
+            if ARCHIVE_EXTENSIONS.contains(&extension.as_str())
+                || !console.accepts_extension(&extension)
+            {
+                return None;
+            }
+            let listed = console
+                .extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(&extension));
+            Some((listed, entry))
+        })
+        .max_by_key(|(listed, entry)| (*listed, entry.size))
+        .map(|(_, entry)| entry);
+
+    Ok(rom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::ConsolesConfig;
+    use std::io::Write;
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_7z(path: &Path, files: &[(&str, &[u8])]) {
+        let mut archive = sevenz_rust::SevenZWriter::create(path).unwrap();
+        for (name, content) in files {
+            let mut entry = sevenz_rust::SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            archive.push_archive_entry(entry, Some(*content)).unwrap();
+        }
+        archive.finish().unwrap();
+    }
+
+    #[test]
+    fn test_is_archive() {
+        assert!(is_archive(Path::new("Game (USA).zip")));
+        assert!(is_archive(Path::new("Game (USA).7Z")));
+        assert!(!is_archive(Path::new("Game (USA).gba")));
+        assert!(!is_archive(Path::new("Game")));
+    }
+
+    #[test]
+    fn test_holds_single_rom() {
+        let temp_dir = std::env::temp_dir().join("collie_test_archive_arcade");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let zip_path = temp_dir.join("sf2.zip");
+        write_zip(
+            &zip_path,
+            &[
+                ("sf2.11e", b"a larger graphics rom"),
+                ("sf2e.30g", b"program"),
+                ("sf2.bin", b"sound"),
+            ],
+        );
+
+        let config = ConsolesConfig::from_embedded().unwrap();
+        let arcade = config.find_console("ARCADE").unwrap();
+        assert!(!holds_single_rom(&zip_path, arcade));
+
+        let gba = config.find_console("GBA").unwrap();
+        assert!(holds_single_rom(&zip_path, gba));
+        assert!(!holds_single_rom(Path::new("sf2.gba"), gba));
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_find_rom_entry_in_zip() {
+        let temp_dir = std::env::temp_dir().join("collie_test_archive_zip");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let zip_path = temp_dir.join("Metroid Fusion (USA).zip");
+        write_zip(
+            &zip_path,
+            &[
+                ("readme.txt", b"a long readme that is larger than the rom"),
+                ("Metroid Fusion (USA).gba", b"rom data"),
+            ],
+        );
+
+        let config = ConsolesConfig::from_embedded().unwrap();
+        let gba = config.find_console("GBA").unwrap();
+        let entry = find_rom_entry(&zip_path, gba).unwrap().unwrap();
+
+        assert_eq!(entry.name, "Metroid Fusion (USA).gba");
+        assert_eq!(entry.size, 8);
+        assert_eq!(entry.crc, crc32fast::hash(b"rom data"));
+
+        // No matching entry for a console with a different extension list
+        let nes = config.find_console("FC").unwrap();
+        assert_eq!(find_rom_entry(&zip_path, nes).unwrap(), None);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_find_rom_entry_in_7z() {
+        let temp_dir = std::env::temp_dir().join("collie_test_archive_7z");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let archive_path = temp_dir.join("Metroid Fusion (USA).7z");
+        write_7z(
+            &archive_path,
+            &[
+                ("readme.txt", b"a long readme that is larger than the rom"),
+                ("Metroid Fusion (USA).gba", b"rom data"),
+            ],
+        );
+
+        let config = ConsolesConfig::from_embedded().unwrap();
+        let gba = config.find_console("GBA").unwrap();
+        let entry = find_rom_entry(&archive_path, gba).unwrap().unwrap();
+
+        assert_eq!(entry.name, "Metroid Fusion (USA).gba");
+        assert_eq!(entry.size, 8);
+        assert_eq!(entry.crc, crc32fast::hash(b"rom data"));
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_seven_zip_entry_without_crc() {
+        let mut file = sevenz_rust::SevenZArchiveEntry::new();
+        file.name = "Metroid Fusion (USA).gba".to_string();
+        file.has_stream = true;
+        file.size = 8;
+        assert_eq!(seven_zip_entry(file.clone()), None);
+
+        file.has_crc = true;
+        file.crc = u64::from(crc32fast::hash(b"rom data"));
+        assert_eq!(
+            seven_zip_entry(file).map(|entry| entry.crc),
+            Some(crc32fast::hash(b"rom data"))
+        );
+    }
+}
@@ -1,5 +1,15 @@
 use serde::Deserialize;
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Extensions accepted for every console that declares its own `extensions` list.
+/// Archives are handled by most cores even when not listed; disc images must be listed.
+const COMMON_EXTENSIONS: &[&str] = &["zip", "7z"];
+
+/// Extensions never treated as ROMs for consoles without an `extensions` list
+const IGNORED_EXTENSIONS: &[&str] = &[
+    "xml", "miyoocmd", "cfg", "db", "nfo", "txt", "srm", "sav", "png", "jpg", "jpeg",
+];
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Console {
@@ -12,6 +22,148 @@ pub struct Console {
     pub thegamesdb_id: Option<i32>,
     #[serde(default)]
     pub gamefaqs_archive_id: Option<String>,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Emulator cores that run this console's games
+    #[serde(default)]
+    pub cores: Vec<String>,
+    /// Exact file names that identify this console's games (e.g. `Doukutsu.exe`)
+    #[serde(default)]
+    pub file_name: Vec<String>,
+    /// ROM headers left out of No-Intro style checksums
+    #[serde(default)]
+    pub headers: Vec<HeaderRule>,
+    /// ROMs are MAME-style romsets named by short name, e.g. `sf2.zip`
+    #[serde(default)]
+    pub arcade: bool,
+}
+
+/// A copier or emulator header that ROM databases leave out of their checksums
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HeaderRule {
+    /// Length of the header in bytes
+    pub size: u64,
+    /// Bytes found at `offset` when the header is present, e.g. "NES\u001A"
+    #[serde(default)]
+    pub magic: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
+    /// Only strip the header when the file length modulo this value equals `size`,
+    /// for headers without a signature such as SNES copier headers
+    #[serde(default)]
+    pub modulo: Option<u64>,
+}
+
+impl HeaderRule {
+    /// Check whether a file starting with `start` and `file_size` bytes long has this header
+    pub fn matches(&self, start: &[u8], file_size: u64) -> bool {
+        if file_size <= self.size {
+            return false;
+        }
+        if let Some(magic) = &self.magic
+            && start.get(self.offset..self.offset + magic.len()) != Some(magic.as_bytes())
+        {
+            return false;
+        }
+        if let Some(modulo) = self.modulo
+            && file_size % modulo != self.size
+        {
+            return false;
+        }
+        true
+    }
+}
+
+impl Console {
+    /// Check whether a file is a ROM for this console, by exact file name or by extension.
+    ///
+    /// Consoles that only declare `file_name` accept nothing else.
+    pub fn accepts_file(&self, file_name: &str) -> bool {
+        if self
+            .file_name
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(file_name))
+        {
+            return true;
+        }
+        if !self.file_name.is_empty() && self.extensions.is_empty() {
+            return false;
+        }
+
+        match Path::new(file_name).extension().and_then(|e| e.to_str()) {
+            Some(extension) => self.accepts_extension(extension),
+            None => self.extensions.is_empty(),
+        }
+    }
+
+    /// Get the size of the header a ROM starts with, or 0 if none of the console's rules match
+    pub fn header_size(&self, start: &[u8], file_size: u64) -> u64 {
+        self.headers
+            .iter()
+            .find(|rule| rule.matches(start, file_size))
+            .map(|rule| rule.size)
+            .unwrap_or(0)
+    }
+
+    /// Check whether a file extension belongs to a ROM for this console.
+    ///
+    /// Consoles with an `extensions` list only accept those plus [`COMMON_EXTENSIONS`];
+    /// consoles without one accept anything not in [`IGNORED_EXTENSIONS`].
+    pub fn accepts_extension(&self, extension: &str) -> bool {
+        let extension = extension.to_lowercase();
+
+        if self.extensions.is_empty() {
+            return !IGNORED_EXTENSIONS.contains(&extension.as_str());
+        }
+
+        COMMON_EXTENSIONS.contains(&extension.as_str())
+            || self
+                .extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(&extension))
+    }
+}
+
+/// Keys allowed in a `[[consoles]]` entry
+const KNOWN_CONSOLE_KEYS: &[&str] = &[
+    "name",
+    "patterns",
+    "screenscraper_id",
+    "thegamesdb_id",
+    "gamefaqs_archive_id",
+    "extensions",
+    "cores",
+    "file_name",
+    "headers",
+    "arcade",
+];
+
+/// A problem found while validating console definitions
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigIssue {
+    /// The same folder pattern is claimed by more than one console
+    DuplicatePattern {
+        pattern: String,
+        consoles: Vec<String>,
+    },
+    /// A key that collie does not understand, most likely a typo
+    UnknownKey { console: String, key: String },
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigIssue::DuplicatePattern { pattern, consoles } => write!(
+                f,
+                "Pattern \"{}\" is used by multiple consoles: {}",
+                pattern,
+                consoles.join(", ")
+            ),
+            ConfigIssue::UnknownKey { console, key } => {
+                write!(f, "Unknown key \"{}\" in console \"{}\"", key, console)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +186,157 @@ impl ConsolesConfig {
         Ok(config)
     }
 
+    /// Get the default path of user console definitions for a ROMs folder
+    pub fn user_config_path(roms_path: &Path) -> PathBuf {
+        roms_path.join(".collie").join("consoles.toml")
+    }
+
+    /// Load the bundled consoles merged with user definitions.
+    ///
+    /// User definitions are read from `path` if given, otherwise from `.collie/consoles.toml`
+    /// when it exists. Returns the merged configuration and any validation issues.
+    pub fn load(
+        roms_path: &Path,
+        path: Option<&Path>,
+    ) -> Result<(Self, Vec<ConfigIssue>), Box<dyn std::error::Error>> {
+        let mut config = Self::from_embedded()?;
+        let mut issues = Vec::new();
+
+        let user_path = path
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| Self::user_config_path(roms_path));
+
+        if path.is_some() || user_path.exists() {
+            let contents = std::fs::read_to_string(&user_path)
+                .map_err(|e| format!("Failed to read {}: {}", user_path.display(), e))?;
+            issues.extend(Self::unknown_keys(&contents)?);
+
+            let user: ConsolesConfig = toml::from_str(&contents)
+                .map_err(|e| format!("Failed to parse {}: {}", user_path.display(), e))?;
+            config.merge(user);
+        }
+
+        issues.extend(config.duplicate_patterns());
+
+        Ok((config, issues))
+    }
+
+    /// Merge user console definitions into this configuration.
+    ///
+    /// Consoles are matched by name: patterns, extensions and cores are extended, while IDs
+    /// and header rules that are set replace the existing ones. Unknown names are added as
+    /// new consoles. A pattern claimed by a user console is removed from every other console.
+    pub fn merge(&mut self, other: ConsolesConfig) {
+        for console in other.consoles {
+            for existing in self
+                .consoles
+                .iter_mut()
+                .filter(|c| !c.name.eq_ignore_ascii_case(&console.name))
+            {
+                existing
+                    .patterns
+                    .retain(|p| !console.patterns.iter().any(|u| u.eq_ignore_ascii_case(p)));
+            }
+
+            let Some(existing) = self
+                .consoles
+                .iter_mut()
+                .find(|c| c.name.eq_ignore_ascii_case(&console.name))
+            else {
+                self.consoles.push(console);
+                continue;
+            };
+
+            extend_unique(&mut existing.patterns, console.patterns);
+            extend_unique(&mut existing.extensions, console.extensions);
+            extend_unique(&mut existing.cores, console.cores);
+            extend_unique(&mut existing.file_name, console.file_name);
+            if !console.headers.is_empty() {
+                existing.headers = console.headers;
+            }
+            existing.arcade |= console.arcade;
+            if console.screenscraper_id.is_some() {
+                existing.screenscraper_id = console.screenscraper_id;
+            }
+            if console.thegamesdb_id.is_some() {
+                existing.thegamesdb_id = console.thegamesdb_id;
+            }
+            if console.gamefaqs_archive_id.is_some() {
+                existing.gamefaqs_archive_id = console.gamefaqs_archive_id;
+            }
+        }
+    }
+
+    /// Report folder patterns that are claimed by more than one console
+    pub fn duplicate_patterns(&self) -> Vec<ConfigIssue> {
+        let mut issues: Vec<ConfigIssue> = Vec::new();
+
+        for (index, console) in self.consoles.iter().enumerate() {
+            for pattern in &console.patterns {
+                let already_reported = issues.iter().any(|issue| {
+                    matches!(issue, ConfigIssue::DuplicatePattern { pattern: p, .. }
+                        if p.eq_ignore_ascii_case(pattern))
+                });
+                if already_reported {
+                    continue;
+                }
+
+                let others: Vec<String> = self.consoles[index + 1..]
+                    .iter()
+                    .filter(|c| c.patterns.iter().any(|p| p.eq_ignore_ascii_case(pattern)))
+                    .map(|c| c.name.clone())
+                    .collect();
+                if !others.is_empty() {
+                    let mut consoles = vec![console.name.clone()];
+                    consoles.extend(others);
+                    issues.push(ConfigIssue::DuplicatePattern {
+                        pattern: pattern.clone(),
+                        consoles,
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Report keys in a consoles TOML document that collie does not understand
+    pub fn unknown_keys(contents: &str) -> Result<Vec<ConfigIssue>, toml::de::Error> {
+        let document: toml::Table = toml::from_str(contents)?;
+        let mut issues = Vec::new();
+
+        for key in document.keys().filter(|k| *k != "consoles") {
+            issues.push(ConfigIssue::UnknownKey {
+                console: String::new(),
+                key: key.clone(),
+            });
+        }
+
+        let consoles = document
+            .get("consoles")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c.as_table());
+        for console in consoles {
+            let name = console
+                .get("name")
+                .and_then(|n| n.as_str())
+                .unwrap_or_default();
+            for key in console
+                .keys()
+                .filter(|k| !KNOWN_CONSOLE_KEYS.contains(&k.as_str()))
+            {
+                issues.push(ConfigIssue::UnknownKey {
+                    console: name.to_string(),
+                    key: key.clone(),
+                });
+            }
+        }
+
+        Ok(issues)
+    }
+
     /// Find a console by folder name pattern
     pub fn find_console(&self, folder_name: &str) -> Option<&Console> {
         self.consoles.iter().find(|console| {
@@ -44,6 +347,23 @@ impl ConsolesConfig {
         })
     }
 
+    /// Find a console whose `file_name` list contains this exact file name
+    pub fn find_console_by_file_name(&self, file_name: &str) -> Option<&Console> {
+        self.consoles.iter().find(|console| {
+            console
+                .file_name
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(file_name))
+        })
+    }
+
+    /// Find the first console with the given ScreenScraper system ID
+    pub fn find_console_by_screenscraper_id(&self, screenscraper_id: i32) -> Option<&Console> {
+        self.consoles
+            .iter()
+            .find(|console| console.screenscraper_id == Some(screenscraper_id))
+    }
+
     /// Get all console patterns
     pub fn all_patterns(&self) -> Vec<String> {
         self.consoles
@@ -53,6 +373,15 @@ impl ConsolesConfig {
     }
 }
 
+/// Append values that are not already present (case-insensitive)
+fn extend_unique(values: &mut Vec<String>, new_values: Vec<String>) {
+    for value in new_values {
+        if !values.iter().any(|v| v.eq_ignore_ascii_case(&value)) {
+            values.push(value);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -80,4 +409,210 @@ mod tests {
         // Test not found
         assert!(config.find_console("NOTEXIST").is_none());
     }
+
+    #[test]
+    fn test_accepts_extension() {
+        let config = ConsolesConfig::from_embedded().unwrap();
+
+        // Console with an extensions list
+        let gba = config.find_console("GBA").unwrap();
+        assert!(gba.accepts_extension("gba"));
+        assert!(gba.accepts_extension("GBA"));
+        assert!(gba.accepts_extension("zip"));
+        assert!(!gba.accepts_extension("srm"));
+        assert!(!gba.accepts_extension("png"));
+
+        // Disc images only for consoles that list them
+        assert!(!gba.accepts_extension("cue"));
+        assert!(!gba.accepts_extension("bin"));
+        let ps = config.find_console("PS").unwrap();
+        assert!(ps.accepts_extension("cue"));
+        assert!(ps.accepts_extension("chd"));
+        assert!(ps.accepts_extension("m3u"));
+
+        // Console without an extensions list falls back to the blocklist
+        let arcade = config.find_console("ARCADE").unwrap();
+        assert!(arcade.extensions.is_empty());
+        assert!(arcade.accepts_extension("zip"));
+        assert!(arcade.accepts_extension("anything"));
+        assert!(!arcade.accepts_extension("xml"));
+        assert!(!arcade.accepts_extension("txt"));
+    }
+
+    #[test]
+    fn test_accepts_file() {
+        let config = ConsolesConfig::from_embedded().unwrap();
+
+        let cave_story = config.find_console_by_file_name("doukutsu.exe").unwrap();
+        assert_eq!(cave_story.name, "Cave Story");
+        assert!(cave_story.accepts_file("Doukutsu.exe"));
+        assert!(!cave_story.accepts_file("DirectX.dll"));
+
+        let gba = config.find_console("GBA").unwrap();
+        assert!(gba.accepts_file("Metroid Fusion (USA).gba"));
+        assert!(!gba.accepts_file("Metroid Fusion (USA).sav"));
+        assert!(!gba.accepts_file("README"));
+
+        assert_eq!(
+            config.find_console_by_screenscraper_id(9).unwrap().name,
+            "Game Boy"
+        );
+    }
+
+    #[test]
+    fn test_header_size() {
+        let config = ConsolesConfig::from_embedded().unwrap();
+
+        let nes = config.find_console("FC").unwrap();
+        assert_eq!(nes.header_size(b"NES\x1A\x02\x01", 40976), 16);
+        assert_eq!(nes.header_size(b"\x00\x00\x00\x00", 40960), 0);
+
+        // SNES copier headers have no signature, only a 512 byte remainder
+        let snes = config.find_console("SFC").unwrap();
+        assert_eq!(snes.header_size(&[0u8; 16], 1024 * 1024 + 512), 512);
+        assert_eq!(snes.header_size(&[0u8; 16], 1024 * 1024), 0);
+
+        let lynx = config.find_console("LYNX").unwrap();
+        assert_eq!(lynx.header_size(b"LYNX\x00", 128 * 1024 + 64), 64);
+    }
+
+    #[test]
+    fn test_embedded_consoles_are_valid() {
+        const CONSOLES_TOML: &str = include_str!("../consoles.toml");
+        let config = ConsolesConfig::from_embedded().unwrap();
+
+        assert_eq!(config.duplicate_patterns(), vec![]);
+        assert_eq!(ConsolesConfig::unknown_keys(CONSOLES_TOML).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_sgb_folder_routing() {
+        // SGB folders belong to the Super Game Boy console, not Game Boy Color
+        let mut config = ConsolesConfig::from_embedded().unwrap();
+        let sgb = config.find_console("SGB").unwrap();
+        assert_eq!(sgb.name, "Super Game Boy");
+        assert_eq!(sgb.screenscraper_id, Some(9));
+
+        // The previous routing can be restored with a user definition
+        let user: ConsolesConfig = toml::from_str(
+            r#"
+            [[consoles]]
+            name = "Game Boy Color"
+            patterns = ["SGB"]
+            "#,
+        )
+        .unwrap();
+        config.merge(user);
+        assert_eq!(config.find_console("SGB").unwrap().name, "Game Boy Color");
+        assert_eq!(config.duplicate_patterns(), vec![]);
+    }
+
+    #[test]
+    fn test_merge_user_consoles() {
+        let mut config = ConsolesConfig::from_embedded().unwrap();
+        let user: ConsolesConfig = toml::from_str(
+            r#"
+            [[consoles]]
+            name = "Game Boy"
+            patterns = ["GB-HACKS"]
+            extensions = ["gbx"]
+            cores = ["sameboy", "gambatte"]
+
+            [[consoles]]
+            name = "SNES"
+            patterns = ["SFC-JP", "MD"]
+            screenscraper_id = 99
+
+            [[consoles]]
+            name = "Homebrew"
+            patterns = ["HOMEBREW"]
+            extensions = ["bin"]
+            "#,
+        )
+        .unwrap();
+
+        config.merge(user);
+
+        let gb = config.find_console("gb-hacks").unwrap();
+        assert_eq!(gb.name, "Game Boy");
+        assert!(gb.patterns.iter().any(|p| p == "GB"));
+        assert!(gb.accepts_extension("gbx"));
+        assert!(gb.accepts_extension("gb"));
+        assert!(gb.cores.iter().any(|c| c == "sameboy"));
+        assert_eq!(gb.cores.iter().filter(|c| *c == "gambatte").count(), 1);
+
+        let snes = config.find_console("SFC-JP").unwrap();
+        assert_eq!(snes.name, "SNES");
+        assert_eq!(snes.screenscraper_id, Some(99));
+        assert_eq!(snes.thegamesdb_id, Some(6));
+
+        // User patterns win over the embedded console that had them
+        assert_eq!(config.find_console("MD").unwrap().name, "SNES");
+
+        let homebrew = config.find_console("HOMEBREW").unwrap();
+        assert_eq!(homebrew.name, "Homebrew");
+        assert_eq!(homebrew.screenscraper_id, None);
+
+        assert_eq!(config.duplicate_patterns(), vec![]);
+    }
+
+    #[test]
+    fn test_validate_user_consoles() {
+        let contents = r#"
+            [[consoles]]
+            name = "Game Boy Hacks"
+            patterns = ["GBH"]
+            screenscrapper_id = 9
+
+            [[consoles]]
+            name = "More Hacks"
+            patterns = ["gbh"]
+            "#;
+
+        assert_eq!(
+            ConsolesConfig::unknown_keys(contents).unwrap(),
+            vec![ConfigIssue::UnknownKey {
+                console: "Game Boy Hacks".to_string(),
+                key: "screenscrapper_id".to_string(),
+            }]
+        );
+
+        let config: ConsolesConfig = toml::from_str(contents).unwrap();
+        assert_eq!(
+            config.duplicate_patterns(),
+            vec![ConfigIssue::DuplicatePattern {
+                pattern: "GBH".to_string(),
+                consoles: vec!["Game Boy Hacks".to_string(), "More Hacks".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_load_user_consoles_file() {
+        let temp_dir = std::env::temp_dir().join("collie_test_user_consoles");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        // Without a user file only the embedded consoles are loaded
+        let (config, issues) = ConsolesConfig::load(&temp_dir, None).unwrap();
+        assert!(config.find_console("GB-HACKS").is_none());
+        assert!(issues.is_empty());
+
+        let user_path = ConsolesConfig::user_config_path(&temp_dir);
+        std::fs::create_dir_all(user_path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &user_path,
+            "[[consoles]]\nname = \"Game Boy\"\npatterns = [\"GB-HACKS\"]\ncolour = true\n",
+        )
+        .unwrap();
+
+        let (config, issues) = ConsolesConfig::load(&temp_dir, None).unwrap();
+        assert_eq!(config.find_console("GB-HACKS").unwrap().name, "Game Boy");
+        assert_eq!(issues.len(), 1);
+
+        // An explicit path must exist
+        assert!(ConsolesConfig::load(&temp_dir, Some(&temp_dir.join("missing.toml"))).is_err());
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
 }
@@ -0,0 +1,406 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::archive;
+use crate::filename::ParsedName;
+use crate::hashing::{Digests, FileHashes, HashIndex};
+use crate::multidisc;
+use crate::scanner::RomFile;
+
+/// How trustworthy a DAT considers a dump
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DumpStatus {
+    #[default]
+    Good,
+    Verified,
+    #[serde(rename = "baddump")]
+    BadDump,
+}
+
+/// A ROM listed in an imported DAT file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatEntry {
+    /// Name of the DAT the entry came from, e.g. "Nintendo - Game Boy Advance"
+    pub dat: String,
+    /// Canonical game name, e.g. "Metroid Fusion (USA)"
+    pub game: String,
+    /// File name of the ROM within the game
+    pub rom: String,
+    pub regions: Vec<String>,
+    pub status: DumpStatus,
+    pub size: Option<u64>,
+    pub crc: Option<String>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+}
+
+/// Logiqx XML `<datafile>`
+#[derive(Debug, Deserialize)]
+struct Datafile {
+    header: Option<DatHeader>,
+    #[serde(default)]
+    game: Vec<DatGame>,
+    #[serde(default)]
+    machine: Vec<DatGame>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatHeader {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatGame {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(default)]
+    rom: Vec<DatRom>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatRom {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@size")]
+    size: Option<u64>,
+    #[serde(rename = "@crc")]
+    crc: Option<String>,
+    #[serde(rename = "@md5")]
+    md5: Option<String>,
+    #[serde(rename = "@sha1")]
+    sha1: Option<String>,
+    #[serde(rename = "@status")]
+    status: Option<String>,
+}
+
+/// Parse the ROM entries of a Logiqx XML DAT, as published by No-Intro and Redump
+pub fn parse_dat(content: &str, fallback_name: &str) -> Result<Vec<DatEntry>, quick_xml::DeError> {
+    let datafile: Datafile = quick_xml::de::from_str(content)?;
+    let dat = datafile
+        .header
+        .and_then(|header| header.name)
+        .unwrap_or_else(|| fallback_name.to_string());
+
+    let mut entries = Vec::new();
+    for game in datafile.game.into_iter().chain(datafile.machine) {
+        let regions = ParsedName::parse(&game.name).regions;
+        for rom in game.rom {
+            let status = match rom.status.as_deref() {
+                // Nothing to match against
+                Some("nodump") => continue,
+                Some("verified") => DumpStatus::Verified,
+                Some("baddump") => DumpStatus::BadDump,
+                _ => DumpStatus::Good,
+            };
+            entries.push(DatEntry {
+                dat: dat.clone(),
+                game: game.name.clone(),
+                rom: rom.name,
+                regions: regions.clone(),
+                status,
+                size: rom.size,
+                crc: rom.crc.map(|h| h.to_lowercase()),
+                md5: rom.md5.map(|h| h.to_lowercase()),
+                sha1: rom.sha1.map(|h| h.to_lowercase()),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// ROM checksums from imported DAT files, stored in `.collie/dats/`
+#[derive(Debug, Default)]
+pub struct DatIndex {
+    entries: Vec<DatEntry>,
+    by_sha1: HashMap<String, usize>,
+    by_md5: HashMap<String, usize>,
+    by_crc: HashMap<(String, u64), usize>,
+}
+
+impl DatIndex {
+    /// Get the folder imported DATs are stored in
+    pub fn dats_dir(roms_path: &Path) -> PathBuf {
+        roms_path.join(".collie").join("dats")
+    }
+
+    /// Import a Logiqx XML DAT file, replacing any earlier import of the same file.
+    ///
+    /// Returns the number of ROMs imported.
+    pub fn import(roms_path: &Path, dat_path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(dat_path)?;
+        let stem = dat_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("dat");
+        let entries = parse_dat(&content, stem)?;
+
+        let dats_dir = Self::dats_dir(roms_path);
+        std::fs::create_dir_all(&dats_dir)?;
+
+        let index_file = dats_dir.join(format!(
+            "{}.jsonl",
+            stem.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+        ));
+        let mut file = File::create(&index_file)?;
+        for entry in &entries {
+            writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        }
+
+        Ok(entries.len())
+    }
+
+    /// Load every imported DAT
+    pub fn load(roms_path: &Path) -> Self {
+        let mut index = Self::default();
+
+        let Ok(dir) = std::fs::read_dir(Self::dats_dir(roms_path)) else {
+            return index;
+        };
+        for path in dir.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("Failed to read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                match serde_json::from_str(&line) {
+                    Ok(entry) => index.insert(entry),
+                    Err(e) => debug!("Skipping invalid DAT index line: {}", e),
+                }
+            }
+        }
+
+        index
+    }
+
+    fn insert(&mut self, entry: DatEntry) {
+        let position = self.entries.len();
+        if let Some(sha1) = &entry.sha1 {
+            self.by_sha1.entry(sha1.clone()).or_insert(position);
+        }
+        if let Some(md5) = &entry.md5 {
+            self.by_md5.entry(md5.clone()).or_insert(position);
+        }
+        if let (Some(crc), Some(size)) = (&entry.crc, entry.size) {
+            self.by_crc.entry((crc.clone(), size)).or_insert(position);
+        }
+        self.entries.push(entry);
+    }
+
+    /// Number of ROMs across all imported DATs
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether no DAT has been imported
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Find the DAT entry matching a file's checksums, preferring headerless checksums
+    pub fn find(&self, hashes: &FileHashes) -> Option<&DatEntry> {
+        hashes
+            .headerless
+            .iter()
+            .chain(std::iter::once(&hashes.raw))
+            .find_map(|digests| self.find_digests(digests))
+    }
+
+    fn find_digests(&self, digests: &Digests) -> Option<&DatEntry> {
+        self.by_sha1
+            .get(&digests.sha1)
+            .or_else(|| self.by_md5.get(&digests.md5))
+            .or_else(|| self.by_crc.get(&(digests.crc32.clone(), digests.size)))
+            .map(|&position| &self.entries[position])
+    }
+
+    /// Find the DAT entry matching a CRC32 and size, as stored in archive directories
+    pub fn find_crc(&self, crc: u32, size: u64) -> Option<&DatEntry> {
+        self.by_crc
+            .get(&(format!("{:08x}", crc), size))
+            .map(|&position| &self.entries[position])
+    }
+
+    /// Identify a scanned ROM by its checksums, without any network call
+    pub async fn identify(&self, rom: &RomFile, hash_index: &HashIndex) -> Option<DatEntry> {
+        if archive::holds_single_rom(&rom.path, &rom.console) {
+            let entry = archive::find_rom_entry(&rom.path, &rom.console)
+                .inspect_err(|e| warn!("Failed to read archive {}: {}", rom.path.display(), e))
+                .ok()
+                .flatten()?;
+            return self.find_crc(entry.crc, entry.size).cloned();
+        }
+
+        for path in dat_files(rom) {
+            let Ok(hashes) = hash_index
+                .hashes(&path, &rom.console)
+                .await
+                .inspect_err(|e| warn!("Failed to hash {}: {}", path.display(), e))
+            else {
+                continue;
+            };
+            if let Some(entry) = self.find(&hashes) {
+                return Some(entry.clone());
+            }
+        }
+        None
+    }
+}
+
+/// Get the files of a ROM to look up in DATs.
+///
+/// Disc games are looked up by their first disc. Redump lists the tracks of a .cue sheet
+/// and the sheet itself, so the tracks come first in case the sheet was edited.
+fn dat_files(rom: &RomFile) -> Vec<PathBuf> {
+    let disc = rom.discs.first().unwrap_or(&rom.path);
+    let is_cue = disc
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("cue"))
+        .unwrap_or(false);
+
+    let mut files = if is_cue {
+        multidisc::playlist_entries(disc)
+    } else {
+        Vec::new()
+    };
+    files.push(disc.clone());
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing::hash_reader;
+
+    const DAT: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/dtds/datafile.dtd">
+<datafile>
+	<header>
+		<name>Nintendo - Game Boy Advance</name>
+		<description>Nintendo - Game Boy Advance</description>
+	</header>
+	<game name="Metroid Fusion (USA)">
+		<description>Metroid Fusion (USA)</description>
+		<rom name="Metroid Fusion (USA).gba" size="8" crc="C0C4FA9A" md5="4A0E76FC5F7E9AE2F3D4E19A8C70EB11" sha1="0000000000000000000000000000000000000000" status="verified"/>
+	</game>
+	<game name="Golden Sun (Europe) (En,Fr,De)">
+		<rom name="Golden Sun (Europe) (En,Fr,De).gba" size="4" crc="12345678" status="baddump"/>
+		<rom name="Missing.gba" status="nodump"/>
+	</game>
+</datafile>
+"#;
+
+    #[test]
+    fn test_parse_dat() {
+        let entries = parse_dat(DAT, "fallback").unwrap();
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].dat, "Nintendo - Game Boy Advance");
+        assert_eq!(entries[0].game, "Metroid Fusion (USA)");
+        assert_eq!(entries[0].regions, ["USA"]);
+        assert_eq!(entries[0].status, DumpStatus::Verified);
+        assert_eq!(entries[0].crc.as_deref(), Some("c0c4fa9a"));
+
+        assert_eq!(entries[1].regions, ["Europe"]);
+        assert_eq!(entries[1].status, DumpStatus::BadDump);
+    }
+
+    #[test]
+    fn test_import_and_match() {
+        let temp_dir = std::env::temp_dir().join("collie_test_dat_import");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let hashes = hash_reader(&b"rom data"[..], 0).unwrap();
+        let dat = DAT.replace("C0C4FA9A", &hashes.raw.crc32.to_uppercase());
+        let dat_path = temp_dir.join("gba.dat");
+        std::fs::write(&dat_path, dat).unwrap();
+
+        assert_eq!(DatIndex::import(&temp_dir, &dat_path).unwrap(), 2);
+
+        let index = DatIndex::load(&temp_dir);
+        assert_eq!(index.len(), 2);
+        let entry = index.find(&hashes).unwrap();
+        assert_eq!(entry.game, "Metroid Fusion (USA)");
+        assert_eq!(
+            index
+                .find_crc(crc32fast::hash(b"rom data"), 8)
+                .unwrap()
+                .game,
+            "Metroid Fusion (USA)"
+        );
+        assert_eq!(index.find_crc(crc32fast::hash(b"rom data"), 9), None);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_identify_disc_by_tracks() {
+        let temp_dir = std::env::temp_dir().join("collie_test_dat_discs");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let ps_dir = temp_dir.join("PS");
+        std::fs::create_dir_all(&ps_dir).unwrap();
+
+        // The local sheet differs from the one Redump lists, the track does not
+        let track = b"PLAYSTATION track data";
+        let cue = "FILE \"Crash Bandicoot (USA).bin\" BINARY\n  TRACK 01 MODE2/2352\n";
+        std::fs::write(ps_dir.join("Crash Bandicoot (USA).bin"), track).unwrap();
+        std::fs::write(ps_dir.join("Crash Bandicoot (USA).cue"), cue).unwrap();
+        std::fs::write(
+            ps_dir.join("Crash Bandicoot (USA).m3u"),
+            "Crash Bandicoot (USA).cue\n",
+        )
+        .unwrap();
+
+        let dat = format!(
+            r#"<?xml version="1.0"?>
+<datafile>
+	<header>
+		<name>Sony - PlayStation</name>
+	</header>
+	<game name="Crash Bandicoot (USA)">
+		<rom name="Crash Bandicoot (USA).cue" size="88" crc="0badc0de"/>
+		<rom name="Crash Bandicoot (USA).bin" size="{}" crc="{:08x}"/>
+	</game>
+</datafile>
+"#,
+            track.len(),
+            crc32fast::hash(track)
+        );
+        let dat_path = temp_dir.join("ps.dat");
+        std::fs::write(&dat_path, dat).unwrap();
+        DatIndex::import(&temp_dir, &dat_path).unwrap();
+        let index = DatIndex::load(&temp_dir);
+        let hash_index = HashIndex::open(&temp_dir);
+
+        let consoles = crate::console::ConsolesConfig::from_embedded().unwrap();
+        let console = consoles.find_console("PS").unwrap().clone();
+
+        let cue_rom = RomFile::new(ps_dir.join("Crash Bandicoot (USA).cue"), console.clone());
+        let entry = index.identify(&cue_rom, &hash_index).await.unwrap();
+        assert_eq!(entry.game, "Crash Bandicoot (USA)");
+        assert_eq!(entry.rom, "Crash Bandicoot (USA).bin");
+
+        // Playlists are identified by their first disc
+        let mut m3u_rom = RomFile::new(ps_dir.join("Crash Bandicoot (USA).m3u"), console);
+        m3u_rom.discs = vec![cue_rom.path.clone()];
+        let entry = index.identify(&m3u_rom, &hash_index).await.unwrap();
+        assert_eq!(entry.game, "Crash Bandicoot (USA)");
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
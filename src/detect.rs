@@ -0,0 +1,201 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::disc::DiscImage;
+
+/// Number of bytes read from the start of a file to sniff its header
+const HEADER_SIZE: usize = 0x200;
+
+/// iNES header magic
+const INES_MAGIC: &[u8] = b"NES\x1A";
+
+/// Nintendo logo bitmap stored at 0x104 in every Game Boy cartridge header
+const GB_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+/// Start of the compressed Nintendo logo stored at 0x04 in every Game Boy Advance header
+const GBA_LOGO_PREFIX: [u8; 16] = [
+    0x24, 0xFF, 0xAE, 0x51, 0x69, 0x9A, 0xA2, 0x21, 0x3D, 0x84, 0x82, 0x0A, 0x84, 0xE4, 0x09, 0xAD,
+];
+
+/// First word of a Nintendo 64 ROM in big-endian (.z64), byte-swapped (.v64) and
+/// little-endian (.n64) order
+const N64_MAGICS: [[u8; 4]; 3] = [
+    [0x80, 0x37, 0x12, 0x40],
+    [0x37, 0x80, 0x40, 0x12],
+    [0x40, 0x12, 0x37, 0x80],
+];
+
+/// A system identified from a ROM's contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedSystem {
+    Nes,
+    GameBoy,
+    GameBoyColor,
+    GameBoyAdvance,
+    Genesis,
+    Nintendo64,
+    PlayStation,
+}
+
+impl DetectedSystem {
+    /// ScreenScraper system ID, used to find the matching console in consoles.toml
+    pub fn screenscraper_id(self) -> i32 {
+        match self {
+            DetectedSystem::Nes => 3,
+            DetectedSystem::GameBoy => 9,
+            DetectedSystem::GameBoyColor => 10,
+            DetectedSystem::GameBoyAdvance => 12,
+            DetectedSystem::Genesis => 1,
+            DetectedSystem::Nintendo64 => 14,
+            DetectedSystem::PlayStation => 57,
+        }
+    }
+}
+
+/// Identify the system of a ROM from its header bytes
+pub fn sniff_header(header: &[u8]) -> Option<DetectedSystem> {
+    if header.starts_with(INES_MAGIC) {
+        return Some(DetectedSystem::Nes);
+    }
+
+    if N64_MAGICS.iter().any(|magic| header.starts_with(magic)) {
+        return Some(DetectedSystem::Nintendo64);
+    }
+
+    // Game Boy Advance: logo at 0x04 and fixed value 0x96 at 0xB2
+    if header.get(0x04..0x14) == Some(&GBA_LOGO_PREFIX) && header.get(0xB2) == Some(&0x96) {
+        return Some(DetectedSystem::GameBoyAdvance);
+    }
+
+    // Game Boy: logo at 0x104, CGB flag at 0x143
+    if header.get(0x104..0x134) == Some(&GB_LOGO) {
+        return match header.get(0x143) {
+            Some(0x80) | Some(0xC0) => Some(DetectedSystem::GameBoyColor),
+            _ => Some(DetectedSystem::GameBoy),
+        };
+    }
+
+    // Genesis: "SEGA MEGA DRIVE" or "SEGA GENESIS" system name at 0x100
+    if header.get(0x100..0x104) == Some(b"SEGA") {
+        return Some(DetectedSystem::Genesis);
+    }
+
+    None
+}
+
+/// Identify the system of a ROM or disc image by reading its contents
+pub fn sniff_file(path: &Path) -> Result<Option<DetectedSystem>, std::io::Error> {
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    File::open(path)?
+        .take(HEADER_SIZE as u64)
+        .read_to_end(&mut header)?;
+
+    if let Some(system) = sniff_header(&header) {
+        return Ok(Some(system));
+    }
+
+    // PlayStation discs carry a SYSTEM.CNF boot file in the root directory
+    if let Some(mut disc) = DiscImage::open(path)?
+        && disc.read_root_file("SYSTEM.CNF")?.is_some()
+    {
+        return Ok(Some(DetectedSystem::PlayStation));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disc::tests::{build_iso, to_raw_mode2};
+
+    fn header_with(offset: usize, bytes: &[u8]) -> Vec<u8> {
+        let mut header = vec![0u8; HEADER_SIZE];
+        header[offset..offset + bytes.len()].copy_from_slice(bytes);
+        header
+    }
+
+    #[test]
+    fn test_sniff_nes() {
+        assert_eq!(
+            sniff_header(&header_with(0, b"NES\x1A\x02\x01")),
+            Some(DetectedSystem::Nes)
+        );
+    }
+
+    #[test]
+    fn test_sniff_game_boy() {
+        let mut header = header_with(0x104, &GB_LOGO);
+        assert_eq!(sniff_header(&header), Some(DetectedSystem::GameBoy));
+
+        header[0x143] = 0x80;
+        assert_eq!(sniff_header(&header), Some(DetectedSystem::GameBoyColor));
+    }
+
+    #[test]
+    fn test_sniff_game_boy_advance() {
+        let mut header = header_with(0x04, &GBA_LOGO_PREFIX);
+        assert_eq!(sniff_header(&header), None);
+
+        header[0xB2] = 0x96;
+        assert_eq!(sniff_header(&header), Some(DetectedSystem::GameBoyAdvance));
+    }
+
+    #[test]
+    fn test_sniff_genesis() {
+        assert_eq!(
+            sniff_header(&header_with(0x100, b"SEGA GENESIS    ")),
+            Some(DetectedSystem::Genesis)
+        );
+        assert_eq!(
+            sniff_header(&header_with(0x100, b"SEGA MEGA DRIVE ")),
+            Some(DetectedSystem::Genesis)
+        );
+
+        // The system name must start with "SEGA"
+        assert_eq!(sniff_header(&header_with(0x100, b"NOT BY SEGA     ")), None);
+    }
+
+    #[test]
+    fn test_sniff_nintendo_64() {
+        for magic in N64_MAGICS {
+            assert_eq!(
+                sniff_header(&header_with(0, &magic)),
+                Some(DetectedSystem::Nintendo64)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sniff_unknown() {
+        assert_eq!(sniff_header(&[0u8; HEADER_SIZE]), None);
+        assert_eq!(sniff_header(&[]), None);
+    }
+
+    #[test]
+    fn test_sniff_playstation_disc() {
+        let temp_dir = std::env::temp_dir().join("collie_test_sniff_disc");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let path = temp_dir.join("game.bin");
+        let image = build_iso(
+            "PLAYSTATION",
+            &[("SYSTEM.CNF", b"BOOT = cdrom:\\SCUS_944.26;1\r\n")],
+        );
+        std::fs::write(&path, to_raw_mode2(&image)).unwrap();
+
+        assert_eq!(
+            sniff_file(&path).unwrap(),
+            Some(DetectedSystem::PlayStation)
+        );
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
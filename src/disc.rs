@@ -0,0 +1,378 @@
+use regex::Regex;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::multidisc;
+
+/// Size of the user data in a CD-ROM sector
+const SECTOR_SIZE: u64 = 2048;
+
+/// Size of a raw CD sector including sync, header and error correction
+const RAW_SECTOR_SIZE: u64 = 2352;
+
+/// Sync pattern at the start of every raw data sector
+const SYNC_PATTERN: [u8; 12] = [
+    0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00,
+];
+
+/// Sector holding the ISO9660 primary volume descriptor
+const PRIMARY_VOLUME_DESCRIPTOR: u64 = 16;
+
+/// Largest file read from a disc image, enough for boot configuration files
+const MAX_FILE_SIZE: u32 = 64 * 1024;
+
+/// Extensions of disc images and the playlists pointing at them (compressed .chd is not read)
+const DISC_EXTENSIONS: &[&str] = &["bin", "iso", "img", "cue", "m3u"];
+
+/// Matches the boot executable in a PlayStation SYSTEM.CNF, e.g. `BOOT = cdrom:\SLUS_005.94;1`
+static BOOT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?im)^\s*BOOT2?\s*=.*?([A-Z]{4})[_-](\d{3})\.?(\d{2})").unwrap());
+
+/// Read-only access to the ISO9660 filesystem of a disc image (.iso, or a .bin data track)
+pub struct DiscImage {
+    file: File,
+    sector_size: u64,
+    data_offset: u64,
+}
+
+impl DiscImage {
+    /// Open a disc image, detecting cooked 2048-byte and raw 2352-byte sectors.
+    ///
+    /// Returns `None` if the file does not contain an ISO9660 filesystem.
+    pub fn open(path: &Path) -> Result<Option<Self>, std::io::Error> {
+        Ok(Self::open_data_track(path)?.and_then(|mut disc| disc.has_filesystem().then_some(disc)))
+    }
+
+    /// Open a data track without requiring a filesystem, for discs that boot from raw sectors.
+    ///
+    /// Returns `None` if the file is too short or uses an unknown sector mode.
+    pub fn open_data_track(path: &Path) -> Result<Option<Self>, std::io::Error> {
+        let mut file = File::open(path)?;
+
+        let mut header = [0u8; 16];
+        if file.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+
+        let (sector_size, data_offset) = if header[..12] == SYNC_PATTERN {
+            // Raw sector: mode 1 data follows the header, mode 2 also has a subheader
+            match header[15] {
+                1 => (RAW_SECTOR_SIZE, 16),
+                2 => (RAW_SECTOR_SIZE, 24),
+                _ => return Ok(None),
+            }
+        } else {
+            (SECTOR_SIZE, 0)
+        };
+
+        Ok(Some(Self {
+            file,
+            sector_size,
+            data_offset,
+        }))
+    }
+
+    /// Check whether the data track holds an ISO9660 primary volume descriptor
+    pub fn has_filesystem(&mut self) -> bool {
+        self.read_sector(PRIMARY_VOLUME_DESCRIPTOR)
+            .map(|descriptor| descriptor[0] == 1 && &descriptor[1..6] == b"CD001")
+            .unwrap_or(false)
+    }
+
+    /// Read the user data of a sector
+    pub fn read_sector(&mut self, lba: u64) -> Result<Vec<u8>, std::io::Error> {
+        let mut buffer = vec![0u8; SECTOR_SIZE as usize];
+        self.file
+            .seek(SeekFrom::Start(lba * self.sector_size + self.data_offset))?;
+        self.file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Get the system identifier from the primary volume descriptor (e.g. "PLAYSTATION")
+    pub fn system_identifier(&mut self) -> Result<String, std::io::Error> {
+        let descriptor = self.read_sector(PRIMARY_VOLUME_DESCRIPTOR)?;
+        Ok(String::from_utf8_lossy(&descriptor[8..40])
+            .trim()
+            .to_string())
+    }
+
+    /// Read a file from the root directory by name, ignoring case and the ";1" version suffix
+    pub fn read_root_file(&mut self, name: &str) -> Result<Option<Vec<u8>>, std::io::Error> {
+        let descriptor = self.read_sector(PRIMARY_VOLUME_DESCRIPTOR)?;
+
+        // The root directory record is embedded in the volume descriptor at offset 156
+        let root = &descriptor[156..190];
+        let root_lba = u32::from_le_bytes([root[2], root[3], root[4], root[5]]) as u64;
+        let root_size = u32::from_le_bytes([root[10], root[11], root[12], root[13]]) as u64;
+
+        let sectors = root_size.div_ceil(SECTOR_SIZE).min(64);
+        for sector in 0..sectors {
+            let data = self.read_sector(root_lba + sector)?;
+
+            let mut offset = 0;
+            while offset < data.len() {
+                let length = data[offset] as usize;
+                // Records never span sectors; a zero length pads to the next sector
+                if length == 0 || offset + length > data.len() {
+                    break;
+                }
+
+                let record = &data[offset..offset + length];
+                let name_length = record.get(32).copied().unwrap_or(0) as usize;
+                if 33 + name_length > length {
+                    offset += length;
+                    continue;
+                }
+                let record_name = String::from_utf8_lossy(&record[33..33 + name_length]);
+                let record_name = record_name.split(';').next().unwrap_or_default();
+
+                if record_name.eq_ignore_ascii_case(name) {
+                    let lba = u32::from_le_bytes([record[2], record[3], record[4], record[5]]);
+                    let size = u32::from_le_bytes([record[10], record[11], record[12], record[13]]);
+                    return self
+                        .read_extent(lba as u64, size.min(MAX_FILE_SIZE))
+                        .map(Some);
+                }
+
+                offset += length;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Extract the product serial from the disc's boot information.
+    ///
+    /// Supports PlayStation (`SLUS-00594` from SYSTEM.CNF) and Sega CD (the product code in
+    /// the boot header). PC Engine CD discs have no serial in their IPL, so give `None`.
+    pub fn serial(&mut self) -> Result<Option<String>, std::io::Error> {
+        let boot = self.read_sector(0)?;
+
+        // Sega CD: "SEGADISCSYSTEM" header with "GM MK-4407 -00" style product code at 0x180
+        if boot.starts_with(b"SEGADISCSYSTEM") {
+            let product = String::from_utf8_lossy(&boot[0x180..0x18E]).to_string();
+            let mut parts = product.split_whitespace();
+            let code = match parts.next() {
+                Some("GM") | Some("AI") => parts.next(),
+                other => other,
+            };
+            return Ok(code.map(str::to_string));
+        }
+
+        // PC Engine CD: the IPL only holds a program name, which databases don't index
+        if let Ok(ipl) = self.read_sector(1)
+            && ipl[0x20..].starts_with(b"PC Engine CD-ROM SYSTEM")
+        {
+            return Ok(None);
+        }
+
+        // PlayStation: boot executable named after the serial
+        if self.has_filesystem()
+            && let Ok(Some(config)) = self.read_root_file("SYSTEM.CNF")
+        {
+            let config = String::from_utf8_lossy(&config);
+            if let Some(captures) = BOOT_RE.captures(&config) {
+                return Ok(Some(format!(
+                    "{}-{}{}",
+                    captures[1].to_uppercase(),
+                    &captures[2],
+                    &captures[3]
+                )));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Read `size` bytes of file data starting at a sector
+    fn read_extent(&mut self, lba: u64, size: u32) -> Result<Vec<u8>, std::io::Error> {
+        let mut content = Vec::with_capacity(size as usize);
+        let mut sector = lba;
+        while content.len() < size as usize {
+            content.extend(self.read_sector(sector)?);
+            sector += 1;
+        }
+        content.truncate(size as usize);
+        Ok(content)
+    }
+}
+
+/// Check whether a file is a disc image or a playlist of disc images
+pub fn is_disc_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| DISC_EXTENSIONS.iter().any(|d| e.eq_ignore_ascii_case(d)))
+        .unwrap_or(false)
+}
+
+/// Read the product serial of a disc image, following .cue and .m3u files to their tracks
+pub fn read_serial(path: &Path) -> Result<Option<String>, std::io::Error> {
+    read_serial_nested(path, 0)
+}
+
+fn read_serial_nested(path: &Path, depth: usize) -> Result<Option<String>, std::io::Error> {
+    let is_playlist = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("cue") || e.eq_ignore_ascii_case("m3u"))
+        .unwrap_or(false);
+
+    if is_playlist {
+        // m3u -> cue -> bin is as deep as playlists go
+        if depth >= 2 {
+            return Ok(None);
+        }
+        for entry in multidisc::playlist_entries(path) {
+            if let Some(serial) = read_serial_nested(&entry, depth + 1)? {
+                return Ok(Some(serial));
+            }
+        }
+        return Ok(None);
+    }
+
+    match DiscImage::open_data_track(path)? {
+        Some(mut disc) => disc.serial(),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Build a minimal cooked ISO9660 image with the given files in its root directory
+    pub(crate) fn build_iso(system_identifier: &str, files: &[(&str, &[u8])]) -> Vec<u8> {
+        let sector = SECTOR_SIZE as usize;
+        let root_lba = 18usize;
+        let mut image = vec![0u8; sector * (root_lba + 1 + files.len())];
+
+        let record = |lba: usize, size: usize, name: &[u8]| {
+            let mut r = vec![0u8; 33 + name.len() + (name.len() + 1) % 2];
+            r[0] = r.len() as u8;
+            r[2..6].copy_from_slice(&(lba as u32).to_le_bytes());
+            r[10..14].copy_from_slice(&(size as u32).to_le_bytes());
+            r[32] = name.len() as u8;
+            r[33..33 + name.len()].copy_from_slice(name);
+            r
+        };
+
+        let pvd = 16 * sector;
+        image[pvd] = 1;
+        image[pvd + 1..pvd + 6].copy_from_slice(b"CD001");
+        let mut identifier = [b' '; 32];
+        identifier[..system_identifier.len()].copy_from_slice(system_identifier.as_bytes());
+        image[pvd + 8..pvd + 40].copy_from_slice(&identifier);
+        let root = record(root_lba, sector, &[0]);
+        image[pvd + 156..pvd + 156 + root.len()].copy_from_slice(&root);
+
+        let mut offset = root_lba * sector;
+        for (index, (name, content)) in files.iter().enumerate() {
+            let lba = root_lba + 1 + index;
+            let entry = record(lba, content.len(), format!("{};1", name).as_bytes());
+            image[offset..offset + entry.len()].copy_from_slice(&entry);
+            offset += entry.len();
+            image[lba * sector..lba * sector + content.len()].copy_from_slice(content);
+        }
+
+        image
+    }
+
+    /// Convert a cooked image to raw mode 2 sectors, as found in PlayStation .bin files
+    pub(crate) fn to_raw_mode2(image: &[u8]) -> Vec<u8> {
+        image
+            .chunks(SECTOR_SIZE as usize)
+            .flat_map(|data| {
+                let mut raw = vec![0u8; RAW_SECTOR_SIZE as usize];
+                raw[..12].copy_from_slice(&SYNC_PATTERN);
+                raw[15] = 2;
+                raw[24..24 + data.len()].copy_from_slice(data);
+                raw
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_root_file() {
+        let temp_dir = std::env::temp_dir().join("collie_test_disc_image");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let image = build_iso(
+            "PLAYSTATION",
+            &[("SYSTEM.CNF", b"BOOT = cdrom:\\SLUS_005.94;1\r\n")],
+        );
+
+        for (name, bytes) in [
+            ("game.iso", image.clone()),
+            ("game.bin", to_raw_mode2(&image)),
+        ] {
+            let path = temp_dir.join(name);
+            std::fs::write(&path, bytes).unwrap();
+
+            let mut disc = DiscImage::open(&path).unwrap().unwrap();
+            assert_eq!(disc.system_identifier().unwrap(), "PLAYSTATION");
+            assert_eq!(
+                disc.read_root_file("system.cnf").unwrap().unwrap(),
+                b"BOOT = cdrom:\\SLUS_005.94;1\r\n"
+            );
+            assert_eq!(disc.read_root_file("MISSING.TXT").unwrap(), None);
+        }
+
+        // Not a disc image
+        let path = temp_dir.join("game.gba");
+        std::fs::write(&path, vec![0u8; 64 * 1024]).unwrap();
+        assert!(DiscImage::open(&path).unwrap().is_none());
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_read_playstation_serial() {
+        let temp_dir = std::env::temp_dir().join("collie_test_disc_serial_psx");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let image = build_iso(
+            "PLAYSTATION",
+            &[("SYSTEM.CNF", b"BOOT = cdrom:\\SLUS_005.94;1\r\nTCB = 4\r\n")],
+        );
+        std::fs::write(temp_dir.join("game.bin"), to_raw_mode2(&image)).unwrap();
+        let cue = temp_dir.join("game.cue");
+        std::fs::write(&cue, "FILE \"game.bin\" BINARY\n  TRACK 01 MODE2/2352\n").unwrap();
+
+        assert!(is_disc_image(&cue));
+        assert_eq!(read_serial(&cue).unwrap(), Some("SLUS-00594".to_string()));
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_read_sega_cd_and_pc_engine_cd_serials() {
+        let temp_dir = std::env::temp_dir().join("collie_test_disc_serial_boot");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let mut sega_cd = build_iso("SEGADISCSYSTEM", &[]);
+        sega_cd[..16].copy_from_slice(b"SEGADISCSYSTEM  ");
+        sega_cd[0x180..0x18E].copy_from_slice(b"GM MK-4407 -00");
+        let path = temp_dir.join("sonic.iso");
+        std::fs::write(&path, &sega_cd).unwrap();
+        assert_eq!(read_serial(&path).unwrap(), Some("MK-4407".to_string()));
+
+        let sector = SECTOR_SIZE as usize;
+        let mut pc_engine = vec![0u8; sector * 2];
+        pc_engine[sector + 0x20..sector + 0x37].copy_from_slice(b"PC Engine CD-ROM SYSTEM");
+        pc_engine[sector + 0x6A..sector + 0x73].copy_from_slice(b"YS I & II");
+        let path = temp_dir.join("ys.bin");
+        std::fs::write(&path, &pc_engine).unwrap();
+        // PC Engine CD program names are not serials
+        assert_eq!(read_serial(&path).unwrap(), None);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,116 @@
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Name of the gitignore-style files read from the ROMs folder and each console folder
+pub const IGNORE_FILE_NAME: &str = ".collieignore";
+
+/// The `.collieignore` line that excluded a path
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExcludeRule {
+    /// The `.collieignore` file the rule came from
+    pub file: PathBuf,
+    /// The rule as written, e.g. `_backup/`
+    pub pattern: String,
+}
+
+impl fmt::Display for ExcludeRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\" in {}", self.pattern, self.file.display())
+    }
+}
+
+/// Exclusion rules from `.collieignore` files, with later folders taking precedence
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeRules {
+    layers: Vec<Gitignore>,
+}
+
+impl ExcludeRules {
+    /// Load the rules in a folder's `.collieignore`, if it has one
+    pub fn load(dir: &Path) -> Self {
+        Self::default().with_folder(dir)
+    }
+
+    /// Add the rules in a subfolder's `.collieignore`, which override the current ones
+    pub fn with_folder(&self, dir: &Path) -> Self {
+        let mut rules = self.clone();
+
+        let file = dir.join(IGNORE_FILE_NAME);
+        if !file.is_file() {
+            return rules;
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        if let Some(e) = builder.add(&file) {
+            warn!("Invalid rule in {}: {}", file.display(), e);
+        }
+        match builder.build() {
+            Ok(gitignore) => rules.layers.push(gitignore),
+            Err(e) => warn!("Failed to load {}: {}", file.display(), e),
+        }
+
+        rules
+    }
+
+    /// Find the rule excluding a path, if any. `!` rules re-include paths excluded by
+    /// a less specific `.collieignore`.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<ExcludeRule> {
+        for layer in self.layers.iter().rev() {
+            match layer.matched(path, is_dir) {
+                Match::Ignore(glob) => {
+                    return Some(ExcludeRule {
+                        file: glob
+                            .from()
+                            .map(Path::to_path_buf)
+                            .unwrap_or_else(|| layer.path().join(IGNORE_FILE_NAME)),
+                        pattern: glob.original().to_string(),
+                    });
+                }
+                Match::Whitelist(_) => return None,
+                Match::None => {}
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console_rules_override_root_rules() {
+        let temp_dir = std::env::temp_dir().join("collie_test_exclude_rules");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let gba_dir = temp_dir.join("GBA");
+        std::fs::create_dir_all(&gba_dir).unwrap();
+
+        std::fs::write(
+            temp_dir.join(IGNORE_FILE_NAME),
+            "# Backups\n_backup/\n*.bios\n",
+        )
+        .unwrap();
+        std::fs::write(gba_dir.join(IGNORE_FILE_NAME), "gba_bios.bin\n!keep.bios\n").unwrap();
+
+        let root = ExcludeRules::load(&temp_dir);
+        let gba = root.with_folder(&gba_dir);
+
+        let rule = gba.matched(&gba_dir.join("_backup"), true).unwrap();
+        assert_eq!(rule.pattern, "_backup/");
+        assert_eq!(rule.file, temp_dir.join(IGNORE_FILE_NAME));
+        assert_eq!(gba.matched(&gba_dir.join("_backup"), false), None);
+
+        let rule = gba.matched(&gba_dir.join("gba_bios.bin"), false).unwrap();
+        assert_eq!(rule.file, gba_dir.join(IGNORE_FILE_NAME));
+
+        assert!(gba.matched(&gba_dir.join("other.bios"), false).is_some());
+        assert_eq!(gba.matched(&gba_dir.join("keep.bios"), false), None);
+        assert_eq!(gba.matched(&gba_dir.join("Golden Sun.gba"), false), None);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
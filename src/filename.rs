@@ -0,0 +1,315 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Matches a parenthesised or bracketed tag, e.g. "(USA)" or "[T+Eng]"
+static TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\(([^)]*)\)|\[([^\]]*)\]").unwrap());
+
+/// Matches a trailing article moved behind a comma, e.g. "Legend of Zelda, The"
+static ARTICLE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+?), (The|A|An|Le|La|Les|L'|Der|Die|Das|El|Los|Il|Lo)(\s+-\s+.*|:.*)?$")
+        .unwrap()
+});
+
+static REVISION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^rev(?:ision)?\s*([0-9a-z.]+)$").unwrap());
+
+static VERSION_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[vV]\s?(\d[\w.]*)$").unwrap());
+
+static DISC_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^(?:disc|disk|cd)\s*(\d+)(?:\s*of\s*\d+)?$").unwrap());
+
+/// Matches GoodTools/TOSEC dump flags such as "b", "b1", "h1C", "a2" or "o"
+static DUMP_FLAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([abfhopt])(?:\d*[A-Z]*\d*)(?:\s.*)?$").unwrap());
+
+static TRANSLATION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^T([+-])([A-Za-z]+)").unwrap());
+
+/// No-Intro and TOSEC region names, and the GoodTools/TOSEC codes that stand for them
+const REGIONS: &[(&str, &str)] = &[
+    ("USA", "USA"),
+    ("U", "USA"),
+    ("US", "USA"),
+    ("Europe", "Europe"),
+    ("E", "Europe"),
+    ("EU", "Europe"),
+    ("Japan", "Japan"),
+    ("J", "Japan"),
+    ("JP", "Japan"),
+    ("World", "World"),
+    ("W", "World"),
+    ("Asia", "Asia"),
+    ("Australia", "Australia"),
+    ("A", "Australia"),
+    ("AU", "Australia"),
+    ("Brazil", "Brazil"),
+    ("B", "Brazil"),
+    ("BR", "Brazil"),
+    ("Canada", "Canada"),
+    ("CA", "Canada"),
+    ("China", "China"),
+    ("C", "China"),
+    ("CN", "China"),
+    ("France", "France"),
+    ("F", "France"),
+    ("FR", "France"),
+    ("Germany", "Germany"),
+    ("G", "Germany"),
+    ("DE", "Germany"),
+    ("Hong Kong", "Hong Kong"),
+    ("HK", "Hong Kong"),
+    ("Italy", "Italy"),
+    ("I", "Italy"),
+    ("IT", "Italy"),
+    ("Korea", "Korea"),
+    ("K", "Korea"),
+    ("KR", "Korea"),
+    ("Netherlands", "Netherlands"),
+    ("NL", "Netherlands"),
+    ("Russia", "Russia"),
+    ("R", "Russia"),
+    ("RU", "Russia"),
+    ("Spain", "Spain"),
+    ("S", "Spain"),
+    ("ES", "Spain"),
+    ("Sweden", "Sweden"),
+    ("SW", "Sweden"),
+    ("SE", "Sweden"),
+    ("Taiwan", "Taiwan"),
+    ("TW", "Taiwan"),
+    ("UK", "UK"),
+    ("GB", "UK"),
+    ("Scandinavia", "Scandinavia"),
+];
+
+/// A GoodTools/TOSEC dump flag from a bracketed tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DumpFlag {
+    /// `[!]` verified good dump
+    Verified,
+    /// `[b]` bad dump
+    Bad,
+    /// `[h]` hack
+    Hack,
+    /// `[T+Eng]` fan translation, with the language it translates to
+    Translation(String),
+    /// `[o]` overdump
+    Overdump,
+    /// `[a]` alternate dump
+    Alternate,
+    /// `[f]` fixed dump
+    Fixed,
+    /// `[p]` pirate
+    Pirate,
+    /// `[t]` trainer
+    Trained,
+}
+
+/// Title and tags parsed from a No-Intro, TOSEC or GoodTools style file name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedName {
+    /// Clean title with a trailing article moved to the front,
+    /// e.g. "The Legend of Zelda - Minish Cap"
+    pub title: String,
+    pub regions: Vec<String>,
+    /// Lowercase language codes, e.g. `["en", "fr"]`
+    pub languages: Vec<String>,
+    pub revision: Option<String>,
+    pub version: Option<String>,
+    pub disc: Option<u32>,
+    pub flags: Vec<DumpFlag>,
+    /// Tags that are none of the above, e.g. "Beta" or "1986"
+    pub tags: Vec<String>,
+}
+
+impl ParsedName {
+    /// Parse a file name without its extension
+    pub fn parse(stem: &str) -> Self {
+        let mut parsed = ParsedName::default();
+
+        let title_end = stem.find(['(', '[']).unwrap_or(stem.len());
+        parsed.title = clean_title(&stem[..title_end]);
+
+        for captures in TAG_RE.captures_iter(&stem[title_end..]) {
+            if let Some(tag) = captures.get(1) {
+                parsed.parse_parenthesised(tag.as_str().trim());
+            } else if let Some(tag) = captures.get(2) {
+                parsed.parse_bracketed(tag.as_str().trim());
+            }
+        }
+
+        parsed
+    }
+
+    /// Check whether the file is a hack, bad dump or fan translation
+    pub fn is_modified(&self) -> bool {
+        self.flags.iter().any(|flag| {
+            matches!(
+                flag,
+                DumpFlag::Bad | DumpFlag::Hack | DumpFlag::Translation(_) | DumpFlag::Trained
+            )
+        })
+    }
+
+    fn parse_parenthesised(&mut self, tag: &str) {
+        if let Some(regions) = parse_regions(tag) {
+            self.regions.extend(regions);
+        } else if let Some(languages) = parse_languages(tag) {
+            self.languages.extend(languages);
+        } else if let Some(captures) = REVISION_RE.captures(tag) {
+            self.revision = Some(captures[1].to_string());
+        } else if let Some(captures) = VERSION_RE.captures(tag) {
+            self.version = Some(captures[1].to_string());
+        } else if let Some(captures) = DISC_RE.captures(tag) {
+            self.disc = captures[1].parse().ok();
+        } else if !tag.is_empty() {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    fn parse_bracketed(&mut self, tag: &str) {
+        if tag == "!" {
+            self.flags.push(DumpFlag::Verified);
+        } else if let Some(captures) = TRANSLATION_RE.captures(tag) {
+            self.flags
+                .push(DumpFlag::Translation(captures[2].to_string()));
+        } else if let Some(captures) = DUMP_FLAG_RE.captures(tag) {
+            self.flags.push(match &captures[1] {
+                "a" => DumpFlag::Alternate,
+                "b" => DumpFlag::Bad,
+                "f" => DumpFlag::Fixed,
+                "h" => DumpFlag::Hack,
+                "o" => DumpFlag::Overdump,
+                "p" => DumpFlag::Pirate,
+                _ => DumpFlag::Trained,
+            });
+        } else if let Some(captures) = DISC_RE.captures(tag) {
+            self.disc = captures[1].parse().ok();
+        } else if !tag.is_empty() {
+            self.tags.push(tag.to_string());
+        }
+    }
+}
+
+/// Trim a title and move a trailing article back to the front
+fn clean_title(title: &str) -> String {
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    match ARTICLE_RE.captures(&title) {
+        Some(captures) => {
+            let article = &captures[2];
+            let separator = if article.ends_with('\'') { "" } else { " " };
+            format!(
+                "{}{}{}{}",
+                article,
+                separator,
+                &captures[1],
+                captures.get(3).map(|m| m.as_str()).unwrap_or("")
+            )
+        }
+        None => title,
+    }
+}
+
+/// Parse a comma or hyphen separated list of regions, e.g. "USA, Europe" or "JU"
+fn parse_regions(tag: &str) -> Option<Vec<String>> {
+    let lookup = |code: &str| {
+        REGIONS
+            .iter()
+            .find(|(name, _)| *name == code)
+            .map(|(_, region)| region.to_string())
+    };
+
+    let regions: Option<Vec<String>> = tag
+        .split([',', '-'])
+        .map(|part| lookup(part.trim()))
+        .collect();
+    if regions.is_some() {
+        return regions;
+    }
+
+    // GoodTools combines single letter codes, e.g. "JU" or "UE"
+    if tag.len() > 1 && tag.chars().all(|c| "JUE".contains(c)) {
+        return tag.chars().map(|c| lookup(&c.to_string())).collect();
+    }
+
+    None
+}
+
+/// Parse a language list, e.g. "En,Fr,De" (No-Intro) or "en-de" (TOSEC)
+fn parse_languages(tag: &str) -> Option<Vec<String>> {
+    let languages: Vec<String> = tag
+        .split([',', '+', '-'])
+        .map(str::trim)
+        .map(|code| {
+            let mut chars = code.chars();
+            match (chars.next(), chars.next(), chars.next()) {
+                (Some(first), Some(second), None)
+                    if first.is_ascii_alphabetic() && second.is_ascii_lowercase() =>
+                {
+                    Some(code.to_lowercase())
+                }
+                _ => None,
+            }
+        })
+        .collect::<Option<_>>()?;
+    Some(languages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_case::test_case;
+
+    #[test]
+    fn test_parse_no_intro_name() {
+        let parsed = ParsedName::parse("Legend of Zelda, The - Minish Cap (USA) (Rev 1) [T+Eng]");
+        assert_eq!(parsed.title, "The Legend of Zelda - Minish Cap");
+        assert_eq!(parsed.regions, ["USA"]);
+        assert_eq!(parsed.revision.as_deref(), Some("1"));
+        assert_eq!(parsed.flags, [DumpFlag::Translation("Eng".to_string())]);
+        assert!(parsed.is_modified());
+    }
+
+    #[test]
+    fn test_parse_languages_and_disc() {
+        let parsed =
+            ParsedName::parse("Final Fantasy VIII (Europe) (En,Fr,De) (Disc 2) (v1.1) (Beta)");
+        assert_eq!(parsed.title, "Final Fantasy VIII");
+        assert_eq!(parsed.regions, ["Europe"]);
+        assert_eq!(parsed.languages, ["en", "fr", "de"]);
+        assert_eq!(parsed.disc, Some(2));
+        assert_eq!(parsed.version.as_deref(), Some("1.1"));
+        assert_eq!(parsed.tags, ["Beta"]);
+        assert!(!parsed.is_modified());
+    }
+
+    #[test]
+    fn test_parse_goodtools_name() {
+        let parsed = ParsedName::parse("Super Mario Bros. 3 (JU) [h1C] [b2] [!]");
+        assert_eq!(parsed.title, "Super Mario Bros. 3");
+        assert_eq!(parsed.regions, ["Japan", "USA"]);
+        assert_eq!(
+            parsed.flags,
+            [DumpFlag::Hack, DumpFlag::Bad, DumpFlag::Verified]
+        );
+    }
+
+    #[test]
+    fn test_parse_tosec_name() {
+        let parsed = ParsedName::parse("Chase H.Q. (1989)(Ocean)(GB)(en)[a2]");
+        assert_eq!(parsed.title, "Chase H.Q.");
+        assert_eq!(parsed.regions, ["UK"]);
+        assert_eq!(parsed.languages, ["en"]);
+        assert_eq!(parsed.tags, ["1989", "Ocean"]);
+        assert_eq!(parsed.flags, [DumpFlag::Alternate]);
+    }
+
+    #[test_case("Advance Wars", "Advance Wars")]
+    #[test_case("Legend of Zelda, The", "The Legend of Zelda")]
+    #[test_case("Adventures of Lolo, The: Part 2", "The Adventures of Lolo: Part 2")]
+    #[test_case("Mario & Luigi - Superstar Saga ", "Mario & Luigi - Superstar Saga")]
+    fn test_clean_title(stem: &str, expected: &str) {
+        assert_eq!(ParsedName::parse(stem).title, expected);
+    }
+}
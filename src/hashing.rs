@@ -0,0 +1,392 @@
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tracing::{debug, warn};
+
+use crate::console::Console;
+
+/// Size of the buffer used when streaming a file through the hashers
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Number of bytes read from the start of a file to check for a header
+const HEADER_PEEK_SIZE: usize = 512;
+
+/// Checksums of a stream of bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Digests {
+    pub size: u64,
+    /// Lowercase hex, zero-padded to 8 digits
+    pub crc32: String,
+    pub md5: String,
+    pub sha1: String,
+}
+
+/// Checksums of a file's contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHashes {
+    /// Checksums of the whole file
+    #[serde(flatten)]
+    pub raw: Digests,
+    /// Checksums without the console's ROM header, when the file has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headerless: Option<Digests>,
+}
+
+impl FileHashes {
+    /// Get the size of the header left out of the headerless checksums
+    pub fn header_size(&self) -> u64 {
+        self.headerless
+            .as_ref()
+            .map(|headerless| self.raw.size - headerless.size)
+            .unwrap_or(0)
+    }
+}
+
+/// CRC32, MD5 and SHA1 hashers fed together
+struct Hashers {
+    crc32: crc32fast::Hasher,
+    md5: Md5,
+    sha1: Sha1,
+    size: u64,
+}
+
+impl Hashers {
+    fn new() -> Self {
+        Self {
+            crc32: crc32fast::Hasher::new(),
+            md5: Md5::new(),
+            sha1: Sha1::new(),
+            size: 0,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.crc32.update(data);
+        self.md5.update(data);
+        self.sha1.update(data);
+        self.size += data.len() as u64;
+    }
+
+    fn finalize(self) -> Digests {
+        Digests {
+            size: self.size,
+            crc32: format!("{:08x}", self.crc32.finalize()),
+            md5: format!("{:x}", self.md5.finalize()),
+            sha1: format!("{:x}", self.sha1.finalize()),
+        }
+    }
+}
+
+/// Compute CRC32, MD5 and SHA1 in a single pass over a reader.
+///
+/// With a non-zero `header_size`, headerless checksums of the bytes after the header
+/// are computed in the same pass.
+pub fn hash_reader(mut reader: impl Read, header_size: u64) -> Result<FileHashes, std::io::Error> {
+    let mut raw = Hashers::new();
+    let mut headerless = (header_size > 0).then(Hashers::new);
+
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        let chunk = &buffer[..bytes_read];
+
+        if let Some(headerless) = headerless.as_mut() {
+            let skip = header_size.saturating_sub(raw.size).min(chunk.len() as u64);
+            headerless.update(&chunk[skip as usize..]);
+        }
+        raw.update(chunk);
+    }
+
+    Ok(FileHashes {
+        raw: raw.finalize(),
+        headerless: headerless.map(Hashers::finalize),
+    })
+}
+
+/// Get the size of the header a file starts with according to a console's rules
+pub fn detect_header_size(path: &Path, console: &Console) -> Result<u64, std::io::Error> {
+    if console.headers.is_empty() {
+        return Ok(0);
+    }
+
+    let file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let mut start = Vec::with_capacity(HEADER_PEEK_SIZE);
+    file.take(HEADER_PEEK_SIZE as u64).read_to_end(&mut start)?;
+
+    Ok(console.header_size(&start, file_size))
+}
+
+/// Compute the checksums of a ROM, with and without its console's header
+pub fn hash_file(path: &Path, console: &Console) -> Result<FileHashes, std::io::Error> {
+    let header_size = detect_header_size(path, console)?;
+    hash_reader(File::open(path)?, header_size)
+}
+
+/// Compute the checksums of a ROM on the blocking thread pool
+pub async fn hash_file_blocking(
+    path: PathBuf,
+    console: Console,
+) -> Result<FileHashes, std::io::Error> {
+    tokio::task::spawn_blocking(move || hash_file(&path, &console))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// A line of the on-disk index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    path: String,
+    /// Modification time in nanoseconds since the Unix epoch
+    mtime: u64,
+    #[serde(flatten)]
+    hashes: FileHashes,
+}
+
+/// Persistent index of file checksums in `.collie/hashes.jsonl`.
+///
+/// Entries are keyed by the path relative to the ROMs folder and are only reused while
+/// the file's size and modification time are unchanged, so unchanged files are never re-read.
+/// An entry is also refreshed when the console's header rules no longer agree with it.
+#[derive(Debug)]
+pub struct HashIndex {
+    root: PathBuf,
+    index_file: PathBuf,
+    entries: Mutex<HashMap<String, IndexEntry>>,
+}
+
+impl HashIndex {
+    /// Open the index for a ROMs folder, compacting it if it holds outdated entries
+    pub fn open(roms_path: &Path) -> Self {
+        let index_file = roms_path.join(".collie").join("hashes.jsonl");
+
+        let mut entries = HashMap::new();
+        let mut lines = 0;
+        if let Ok(file) = File::open(&index_file) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                lines += 1;
+                // Later lines replace earlier ones for the same path
+                match serde_json::from_str::<IndexEntry>(&line) {
+                    Ok(entry) => {
+                        entries.insert(entry.path.clone(), entry);
+                    }
+                    Err(e) => debug!("Skipping invalid hash index line: {}", e),
+                }
+            }
+        }
+
+        let index = Self {
+            root: roms_path.to_path_buf(),
+            index_file,
+            entries: Mutex::new(entries),
+        };
+
+        if lines > index.len()
+            && let Err(e) = index.compact()
+        {
+            warn!("Failed to compact {}: {}", index.index_file.display(), e);
+        }
+
+        index
+    }
+
+    /// Number of files in the index
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Check whether the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the stored checksums of a ROM if it has not changed since it was hashed
+    pub fn get(&self, path: &Path, console: &Console) -> Option<FileHashes> {
+        let (size, mtime) = file_stamp(path).ok()?;
+        let hashes = {
+            let entries = self.entries.lock().unwrap();
+            let entry = entries.get(&self.key(path))?;
+            if entry.hashes.raw.size != size || entry.mtime != mtime {
+                return None;
+            }
+            entry.hashes.clone()
+        };
+
+        let header_size = detect_header_size(path, console).ok()?;
+        (hashes.header_size() == header_size).then_some(hashes)
+    }
+
+    /// Get the checksums of a ROM, hashing it on the blocking thread pool if it is new or changed
+    pub async fn hashes(
+        &self,
+        path: &Path,
+        console: &Console,
+    ) -> Result<FileHashes, std::io::Error> {
+        if let Some(hashes) = self.get(path, console) {
+            return Ok(hashes);
+        }
+
+        let (_, mtime) = file_stamp(path)?;
+        debug!("Hashing {}", path.display());
+        let hashes = hash_file_blocking(path.to_path_buf(), console.clone()).await?;
+
+        let entry = IndexEntry {
+            path: self.key(path),
+            mtime,
+            hashes: hashes.clone(),
+        };
+        if let Err(e) = self.append(&entry) {
+            warn!("Failed to update {}: {}", self.index_file.display(), e);
+        }
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(entry.path.clone(), entry);
+
+        Ok(hashes)
+    }
+
+    /// Get the index key of a file: its path relative to the ROMs folder with forward slashes
+    fn key(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    /// Append an entry to the index file
+    fn append(&self, entry: &IndexEntry) -> Result<(), std::io::Error> {
+        if let Some(parent) = self.index_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.index_file)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    /// Rewrite the index file with one line per file
+    fn compact(&self) -> Result<(), std::io::Error> {
+        let entries = self.entries.lock().unwrap();
+        let mut content = String::new();
+        for entry in entries.values() {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+        std::fs::write(&self.index_file, content)
+    }
+}
+
+/// Get the size and modification time of a file
+fn file_stamp(path: &Path) -> Result<(u64, u64), std::io::Error> {
+    let metadata = std::fs::metadata(path)?;
+    let mtime = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    Ok((metadata.len(), mtime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::ConsolesConfig;
+
+    #[test]
+    fn test_hash_reader() {
+        let hashes = hash_reader(&b"The quick brown fox jumps over the lazy dog"[..], 0).unwrap();
+        assert_eq!(hashes.raw.size, 43);
+        assert_eq!(hashes.raw.crc32, "414fa339");
+        assert_eq!(hashes.raw.md5, "9e107d9d372bb6826bd81d3542a419d6");
+        assert_eq!(hashes.raw.sha1, "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12");
+        assert_eq!(hashes.headerless, None);
+    }
+
+    #[test]
+    fn test_hash_file_strips_ines_header() {
+        let temp_dir = std::env::temp_dir().join("collie_test_hash_headerless");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let prg = vec![0xEAu8; 0x4000];
+        let mut rom = b"NES\x1A\x01\x00".to_vec();
+        rom.resize(16, 0);
+        rom.extend(&prg);
+
+        let path = temp_dir.join("game.nes");
+        std::fs::write(&path, &rom).unwrap();
+
+        let config = ConsolesConfig::from_embedded().unwrap();
+        let nes = config.find_console("FC").unwrap();
+        let hashes = hash_file(&path, nes).unwrap();
+        assert_eq!(hashes.raw, hash_reader(&rom[..], 0).unwrap().raw);
+        assert_eq!(
+            hashes.headerless,
+            Some(hash_reader(&prg[..], 0).unwrap().raw)
+        );
+        assert_eq!(hashes.header_size(), 16);
+
+        // Consoles without header rules only get raw checksums
+        let gba = config.find_console("GBA").unwrap();
+        assert_eq!(hash_file(&path, gba).unwrap().headerless, None);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_hash_index_reuses_unchanged_files() {
+        let temp_dir = std::env::temp_dir().join("collie_test_hash_index");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(temp_dir.join("GBA")).unwrap();
+
+        let rom = temp_dir.join("GBA").join("game.gba");
+        std::fs::write(&rom, "rom data").unwrap();
+
+        let config = ConsolesConfig::from_embedded().unwrap();
+        let gba = config.find_console("GBA").unwrap();
+
+        let index = HashIndex::open(&temp_dir);
+        assert_eq!(index.get(&rom, gba), None);
+        let hashes = index.hashes(&rom, gba).await.unwrap();
+        assert_eq!(
+            hashes.raw.crc32,
+            format!("{:08x}", crc32fast::hash(b"rom data"))
+        );
+
+        // A new index reads the stored entry back
+        let index = HashIndex::open(&temp_dir);
+        assert_eq!(index.get(&rom, gba), Some(hashes.clone()));
+        let content =
+            std::fs::read_to_string(temp_dir.join(".collie").join("hashes.jsonl")).unwrap();
+        assert!(content.contains("\"path\":\"GBA/game.gba\""));
+
+        // Changing the file invalidates the entry
+        std::fs::write(&rom, "new rom data").unwrap();
+        assert_eq!(index.get(&rom, gba), None);
+        let changed = index.hashes(&rom, gba).await.unwrap();
+        assert_eq!(changed.raw.size, 12);
+
+        // Reopening compacts the outdated line away
+        let index = HashIndex::open(&temp_dir);
+        assert_eq!(index.len(), 1);
+        let content =
+            std::fs::read_to_string(temp_dir.join(".collie").join("hashes.jsonl")).unwrap();
+        assert_eq!(content.lines().count(), 1);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -1,30 +1,43 @@
+pub mod arcade;
+pub mod archive;
 pub mod backoff;
 pub mod cache;
 pub mod console;
+pub mod dat;
+pub mod detect;
+pub mod disc;
+pub mod exclude;
+pub mod filename;
 pub mod gamelist;
+pub mod hashing;
 pub mod image;
+pub mod multidisc;
+pub mod overrides;
 pub mod progress;
+pub mod rename;
 pub mod scanner;
 pub mod scraper;
 pub mod scraping;
 pub mod storage;
 
+use arcade::ArcadeIndex;
 use backoff::BackoffState;
 use console::ConsolesConfig;
+use dat::DatIndex;
 use scanner::RomScanner;
 use scraper::{GuidesScraper, MetadataScraper};
 use storage::{append_crawled_path, append_scraped_index, save_game_data};
-use tracing::info;
+use tracing::{info, warn};
 
 // Re-export commonly used types
 pub use progress::{
     GameData, GameGuides, GameMetadata, ProgressUpdate, ScrapeStatus, ScrapingProgress,
     send_progress,
 };
-pub use scraping::{ScrapingConfig, scrape_game_guides, scrape_game_metadata};
+pub use scraping::{MediaLayout, ScrapingConfig, scrape_game_guides, scrape_game_metadata};
 
 pub async fn scrape(
-    metadata_scrapers: Vec<Box<dyn MetadataScraper>>,
+    mut metadata_scrapers: Vec<Box<dyn MetadataScraper>>,
     guides_scrapers: Vec<Box<dyn GuidesScraper>>,
     config: ScrapingConfig,
     cancel_token: tokio_util::sync::CancellationToken,
@@ -33,15 +46,61 @@ pub async fn scrape(
     // Initialize backoff state for rate limiting
     let mut backoff = BackoffState::new();
 
-    // Load console configuration
-    let consoles_config = ConsolesConfig::from_embedded()
-        .map_err(|e| format!("Failed to load console config: {}", e))?;
+    // Load console configuration, merged with user definitions
+    let (consoles_config, issues) =
+        ConsolesConfig::load(&config.roms_path, config.consoles_path.as_deref())
+            .map_err(|e| format!("Failed to load console config: {}", e))?;
+    for issue in issues {
+        warn!("Console config: {}", issue);
+    }
 
     // Scan for ROMs
-    let scanner = RomScanner::new(consoles_config);
-    let rom_files = scanner
+    let scanner = RomScanner::new(consoles_config)
+        .with_max_depth(config.max_scan_depth)
+        .with_detect_unknown_folders(config.detect_unknown_folders)
+        .with_ignored_folders(vec![
+            config.images_folder.clone(),
+            config.guides_folder.clone(),
+        ]);
+    let scan_summary = scanner
         .scan_directory(&config.roms_path)
         .map_err(|e| format!("Failed to scan ROMs: {}", e))?;
+    let excluded: Vec<String> = scan_summary
+        .excluded()
+        .map(|(path, rule)| {
+            let path = path.strip_prefix(&config.roms_path).unwrap_or(path);
+            format!("{} by {}", path.display(), rule)
+        })
+        .collect();
+    let skipped_files = scan_summary.skipped.len() - excluded.len();
+    let mut rom_files = scan_summary.roms;
+
+    // Write .m3u playlists for multi-disc games
+    if config.generate_m3u {
+        for rom in rom_files.iter_mut().filter(|r| r.discs.len() > 1) {
+            if rom.discs.contains(&rom.path) {
+                match multidisc::write_m3u(rom, config.hide_discs) {
+                    Ok(playlist) => *rom = playlist,
+                    Err(e) => tracing::warn!("Failed to write playlist for {}: {}", rom.name, e),
+                }
+            }
+        }
+    }
+
+    // Load checksums from imported DAT files for offline identification
+    let dat_index = DatIndex::load(&config.roms_path);
+    if !dat_index.is_empty() {
+        info!("Loaded {} ROMs from imported DAT files", dat_index.len());
+    }
+
+    // Load romset names from imported MAME/FBNeo lists for arcade consoles
+    let arcade_index = ArcadeIndex::load(&config.roms_path);
+    if !arcade_index.is_empty() {
+        info!(
+            "Loaded {} sets from imported arcade lists",
+            arcade_index.len()
+        );
+    }
 
     let total = rom_files.len();
     let mut progress = progress::ScrapingProgress {
@@ -51,22 +110,33 @@ pub async fn scrape(
         success_count: 0,
         fail_count: 0,
         skip_count: 0,
+        quotas: Default::default(),
     };
 
     info!("\n========================================");
     info!("Starting ROM Scraping");
     info!("========================================");
-    info!("Found {} ROMs to process\n", total);
+    info!("Found {} ROMs to process", total);
+    info!("Skipped {} non-ROM files\n", skipped_files);
 
     progress::send_progress(
         &progress_tx,
         &progress,
-        format!("Found {} ROMs to process", total),
+        format!(
+            "Found {} ROMs to process ({} non-ROM files skipped, {} paths excluded)",
+            total,
+            skipped_files,
+            excluded.len()
+        ),
         None,
     );
+    for path in excluded {
+        progress::send_progress(&progress_tx, &progress, format!("Excluded {}", path), None);
+    }
 
     let mut cancelled = false;
-    for rom in rom_files {
+    let mut quota_reached = false;
+    for mut rom in rom_files {
         // Check if cancellation was requested
         if cancel_token.is_cancelled() {
             info!("\n⚠ Scraping cancelled by user");
@@ -80,6 +150,24 @@ pub async fn scrape(
             break;
         }
 
+        // Drop backends before their daily quota runs out, and stop once none is left rather
+        // than failing every remaining game
+        let exhausted = drop_exhausted(&mut metadata_scrapers);
+        for backend in &exhausted {
+            let message = format!("Daily quota reached for {}, continuing without it", backend);
+            info!("\n⚠ {}", message);
+            progress::send_progress(&progress_tx, &progress, message, None);
+        }
+        if !exhausted.is_empty() && metadata_scrapers.is_empty() {
+            let message = "Stopping: daily quota reached for every backend. Run again tomorrow to \
+                           scrape the remaining games"
+                .to_string();
+            info!("\n⚠ {}", message);
+            progress::send_progress(&progress_tx, &progress, message, None);
+            quota_reached = true;
+            break;
+        }
+
         progress.current_rom = Some(rom.name.clone());
 
         info!(
@@ -90,6 +178,30 @@ pub async fn scrape(
             rom.console.name
         );
 
+        if !dat_index.is_empty() {
+            rom.dat_match = dat_index.identify(&rom, &config.hash_index).await;
+            match &rom.dat_match {
+                Some(dat_match) => info!(
+                    "  Identified as {} ({}, {:?})",
+                    dat_match.game, dat_match.dat, dat_match.status
+                ),
+                None => info!("  Not found in imported DAT files"),
+            }
+        }
+
+        if rom.console.arcade {
+            rom.arcade_set = arcade_index.get(&rom.name_no_extension).cloned();
+            match &rom.arcade_set {
+                Some(set) if set.is_clone() => info!(
+                    "  Arcade set: {} (clone of {})",
+                    set.description,
+                    set.clone_of.as_deref().unwrap_or_default()
+                ),
+                Some(set) => info!("  Arcade set: {}", set.description),
+                None => {}
+            }
+        }
+
         // Create initial game data entry
         let mut game_data = progress::GameData {
             rom_name: rom.name.clone(),
@@ -103,6 +215,8 @@ pub async fn scrape(
                 rating: None,
                 image_path: None,
                 error_message: None,
+                confidence: None,
+                pinned_ids: Default::default(),
             },
             guides: progress::GameGuides {
                 status: progress::ScrapeStatus::Pending,
@@ -117,20 +231,16 @@ pub async fn scrape(
             Some(game_data.clone()),
         );
 
-        scraping::scrape_game_metadata(
-            &metadata_scrapers,
-            &rom,
-            &config,
-            &mut game_data,
-            &progress,
-            &progress_tx,
-            &mut backoff,
-        )
-        .await;
-
-        let status = if !guides_scrapers.is_empty() {
-            scraping::scrape_game_guides(
-                &guides_scrapers,
+        let status = if let Some(set) = rom.arcade_set.as_ref().filter(|set| !set.is_playable()) {
+            // BIOS and device sets are not games and have no metadata or guides
+            info!("  Skipping {} set", set.kind);
+            game_data.metadata.status = progress::ScrapeStatus::Skipped;
+            game_data.metadata.error_message = Some(format!("Arcade {} set", set.kind));
+            game_data.guides.status = progress::ScrapeStatus::Skipped;
+            progress::ScrapeStatus::Skipped
+        } else {
+            scraping::scrape_game_metadata(
+                &metadata_scrapers,
                 &rom,
                 &config,
                 &mut game_data,
@@ -140,11 +250,31 @@ pub async fn scrape(
             )
             .await;
 
-            game_data.metadata.status.merge(game_data.guides.status)
-        } else {
-            game_data.metadata.status
+            if !guides_scrapers.is_empty() {
+                scraping::scrape_game_guides(
+                    &guides_scrapers,
+                    &rom,
+                    &config,
+                    &mut game_data,
+                    &progress,
+                    &progress_tx,
+                    &mut backoff,
+                )
+                .await;
+
+                game_data.metadata.status.merge(game_data.guides.status)
+            } else {
+                game_data.metadata.status
+            }
         };
 
+        // Dropped backends keep their last reported quota
+        for scraper in &metadata_scrapers {
+            if let Some(quota) = scraper.quota() {
+                progress.quotas.insert(scraper.name().to_string(), quota);
+            }
+        }
+
         if status == progress::ScrapeStatus::Success {
             progress.success_count += 1;
         } else if status == progress::ScrapeStatus::Skipped {
@@ -176,7 +306,13 @@ pub async fn scrape(
     // Save all gamelists
     progress.current_rom = None;
 
-    let status_word = if cancelled { "Cancelled" } else { "Complete" };
+    let status_word = if cancelled {
+        "Cancelled"
+    } else if quota_reached {
+        "Stopped"
+    } else {
+        "Complete"
+    };
     info!("\n========================================");
     info!("Scraping {}!", status_word);
     info!("========================================");
@@ -202,3 +338,102 @@ pub async fn scrape(
 
     Ok(progress)
 }
+
+/// Remove the metadata scrapers that are out of daily quota, describing each one removed
+fn drop_exhausted(metadata_scrapers: &mut Vec<Box<dyn MetadataScraper>>) -> Vec<String> {
+    let mut exhausted = Vec::new();
+    metadata_scrapers.retain(|scraper| {
+        let Some(quota) = scraper.quota().filter(|quota| quota.is_exhausted()) else {
+            return true;
+        };
+        exhausted.push(format!(
+            "{} ({}/{} requests)",
+            scraper.name(),
+            quota.requests_today,
+            quota.max_requests_per_day
+        ));
+        false
+    });
+    exhausted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scraper::{GameMetadata, Quota, ScraperError, ScraperResult};
+    use std::path::Path;
+
+    /// A backend that only reports a quota
+    struct QuotaScraper {
+        name: &'static str,
+        quota: Option<Quota>,
+    }
+
+    #[async_trait::async_trait]
+    impl MetadataScraper for QuotaScraper {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn quota(&self) -> Option<Quota> {
+            self.quota
+        }
+
+        async fn authenticate(&mut self, _username: &str, _password: &str) -> ScraperResult<()> {
+            Ok(())
+        }
+
+        async fn search_game(&self, _rom: &scanner::RomFile) -> ScraperResult<GameMetadata> {
+            Err(ScraperError::GameNotFound)
+        }
+
+        async fn get_game_metadata(&self, _game_id: &str) -> ScraperResult<GameMetadata> {
+            Err(ScraperError::GameNotFound)
+        }
+
+        async fn download_image(&self, _url: &str, _destination: &Path) -> ScraperResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_drop_exhausted() {
+        let used_up = Quota {
+            requests_today: 19995,
+            max_requests_per_day: 20000,
+            ..Default::default()
+        };
+        let mut scrapers: Vec<Box<dyn MetadataScraper>> = vec![
+            Box::new(QuotaScraper {
+                name: "ScreenScraper",
+                quota: Some(used_up),
+            }),
+            Box::new(QuotaScraper {
+                name: "TheGamesDB",
+                quota: None,
+            }),
+        ];
+
+        // Backends without a quota are never dropped
+        assert_eq!(
+            drop_exhausted(&mut scrapers),
+            vec!["ScreenScraper (19995/20000 requests)"]
+        );
+        assert_eq!(scrapers.len(), 1);
+        assert_eq!(scrapers[0].name(), "TheGamesDB");
+        assert!(drop_exhausted(&mut scrapers).is_empty());
+        assert_eq!(scrapers.len(), 1);
+
+        // A backend with quota left is kept
+        let mut scrapers: Vec<Box<dyn MetadataScraper>> = vec![Box::new(QuotaScraper {
+            name: "ScreenScraper",
+            quota: Some(Quota {
+                requests_today: 100,
+                max_requests_per_day: 20000,
+                ..Default::default()
+            }),
+        })];
+        assert!(drop_exhausted(&mut scrapers).is_empty());
+        assert_eq!(scrapers.len(), 1);
+    }
+}
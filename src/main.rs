@@ -5,15 +5,21 @@ use axum::{
     routing::{get, post},
 };
 use clap::Parser;
+use collie::scraper::http::{HttpClient, HttpConfig};
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{Mutex, broadcast};
 use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
 use tracing::Level;
 
+use server::api::candidates::{choose_candidate, search_candidates};
+use server::api::credentials::check_screenscraper;
 use server::api::directories::list_directories;
 use server::api::games::{get_game_by_rom_name, get_games};
 use server::api::images::serve_image;
+use server::api::overrides::{get_overrides, set_override};
+use server::api::rename::{rename, undo_rename};
 use server::api::scrape::{
     get_state, progress_stream, save_settings, start_scraping, stop_scraping,
 };
@@ -38,6 +44,26 @@ struct Args {
     /// Don't launch the web interface in the default browser
     #[arg(long)]
     no_launch: bool,
+
+    /// Console definitions to merge with the built-in list [default: .collie/consoles.toml]
+    #[arg(long)]
+    consoles: Option<PathBuf>,
+
+    /// Import a Logiqx XML DAT file (No-Intro, Redump) into `.collie/dats` (can be repeated)
+    #[arg(long, value_name = "FILE")]
+    import_dat: Vec<PathBuf>,
+
+    /// Import a MAME/FBNeo `-listxml` output or arcade DAT into `.collie/arcade` (can be repeated)
+    #[arg(long, value_name = "FILE")]
+    import_arcade: Vec<PathBuf>,
+
+    /// HTTP or SOCKS proxy for scraper requests, e.g. socks5://127.0.0.1:1080
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Requests per second sent to each scraper host, 0 for no limit
+    #[arg(long, default_value_t = 2.0)]
+    requests_per_second: f64,
 }
 
 #[tokio::main]
@@ -72,6 +98,43 @@ async fn main() {
         );
     }
 
+    // Check console definitions before starting so mistakes are reported early
+    match collie::console::ConsolesConfig::load(&roms_path, args.consoles.as_deref()) {
+        Ok((_, issues)) => {
+            for issue in issues {
+                eprintln!("Warning: {}", issue);
+            }
+        }
+        Err(e) => eprintln!("Failed to load console config: {}", e),
+    }
+
+    for dat_path in &args.import_dat {
+        match collie::dat::DatIndex::import(&roms_path, dat_path) {
+            Ok(count) => println!("Imported {} ROMs from {}", count, dat_path.display()),
+            Err(e) => eprintln!("Failed to import {}: {}", dat_path.display(), e),
+        }
+    }
+
+    for list_path in &args.import_arcade {
+        match collie::arcade::ArcadeIndex::import(&roms_path, list_path) {
+            Ok(count) => println!("Imported {} sets from {}", count, list_path.display()),
+            Err(e) => eprintln!("Failed to import {}: {}", list_path.display(), e),
+        }
+    }
+
+    let http_config = HttpConfig {
+        proxy: args.proxy,
+        requests_per_second: args.requests_per_second,
+        ..Default::default()
+    };
+    let http_client = match HttpClient::new(http_config) {
+        Ok(http_client) => Arc::new(http_client),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     let (progress_tx, _) = broadcast::channel(PROGRESS_CHANNEL_SIZE);
 
     let state = AppState {
@@ -79,19 +142,27 @@ async fn main() {
         cancel_token: Arc::new(Mutex::new(None)),
         progress_tx,
         roms_path: Arc::new(std::sync::Mutex::new(roms_path)),
+        consoles_path: args.consoles,
         scraping_state: Arc::new(Mutex::new(initial_state)),
+        http_client,
     };
 
     let app = Router::new()
         .route("/api/settings", post(save_settings))
         .route("/api/scrape", post(start_scraping))
         .route("/api/stop", post(stop_scraping))
+        .route("/api/rename", post(rename))
+        .route("/api/rename/undo", post(undo_rename))
         .route("/api/state", post(get_state))
         .route("/api/directories", post(list_directories))
         .route("/api/games", get(get_games))
         .route("/api/games/{rom_name}", get(get_game_by_rom_name))
         .route("/api/progress", get(progress_stream))
         .route("/api/images/{*path}", get(serve_image))
+        .route("/api/overrides", get(get_overrides).post(set_override))
+        .route("/api/candidates", post(search_candidates))
+        .route("/api/candidates/choose", post(choose_candidate))
+        .route("/api/credentials/screenscraper", post(check_screenscraper))
         .with_state(state)
         .fallback(static_handler)
         .layer(
@@ -0,0 +1,318 @@
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tracing::{debug, info};
+
+use crate::scanner::RomFile;
+
+/// Matches disc tags such as "(Disc 1)", "(Disc 2 of 3)", "[CD1]" or "(Disk 2)"
+static DISC_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\s*[\(\[](?:disc|disk|cd)\s*(\d+)(?:\s*of\s*\d+)?[\)\]]").unwrap()
+});
+
+/// Split a file stem into the disc set title and disc number,
+/// e.g. "Final Fantasy VII (USA) (Disc 2)" -> ("Final Fantasy VII (USA)", 2)
+pub fn parse_disc_number(stem: &str) -> Option<(String, u32)> {
+    let captures = DISC_RE.captures(stem)?;
+    let number = captures.get(1)?.as_str().parse().ok()?;
+    let title = DISC_RE
+        .replace(stem, "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some((title, number))
+}
+
+/// Check whether a file is a playlist that references other files (.cue or .m3u)
+fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("cue") || e.eq_ignore_ascii_case("m3u"))
+        .unwrap_or(false)
+}
+
+/// Read the files referenced by a .cue sheet or .m3u playlist, resolved against its folder
+pub fn playlist_entries(path: &Path) -> Vec<PathBuf> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let is_cue = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("cue"))
+        .unwrap_or(false);
+
+    content
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| {
+            if is_cue {
+                // FILE "Game (Track 1).bin" BINARY
+                let rest = line.strip_prefix("FILE ")?.trim();
+                let name = match rest.strip_prefix('"') {
+                    Some(quoted) => quoted.split('"').next()?,
+                    None => rest.split_whitespace().next()?,
+                };
+                Some(name)
+            } else if line.is_empty() || line.starts_with('#') {
+                None
+            } else {
+                Some(line)
+            }
+        })
+        .map(|name| dir.join(name.replace('\\', "/")))
+        .collect()
+}
+
+/// Group scanned ROMs into logical games.
+///
+/// Files referenced by a .cue or .m3u are folded into that playlist's entry, and
+/// "(Disc N)" files sharing a title in the same folder are folded into the first disc,
+/// which keeps its own name until a playlist replaces it.
+pub fn group_discs(roms: Vec<RomFile>) -> Vec<RomFile> {
+    // Map every playlist to the files it references
+    let references: HashMap<PathBuf, Vec<PathBuf>> = roms
+        .iter()
+        .filter(|rom| is_playlist(&rom.path))
+        .map(|rom| (rom.path.clone(), playlist_entries(&rom.path)))
+        .collect();
+    let owned: HashSet<&PathBuf> = references.values().flatten().collect();
+
+    // Keep only ROMs no playlist refers to, collecting everything they own
+    let mut roots: Vec<RomFile> = roms
+        .iter()
+        .filter(|rom| !owned.contains(&rom.path))
+        .cloned()
+        .map(|mut rom| {
+            let mut pending = references.get(&rom.path).cloned().unwrap_or_default();
+            while let Some(path) = pending.pop() {
+                if rom.companions.contains(&path) {
+                    continue;
+                }
+                // Playlists in hidden folders are not scanned, so read them here
+                if is_playlist(&path) {
+                    match references.get(&path) {
+                        Some(nested) => pending.extend(nested.iter().cloned()),
+                        None => pending.extend(playlist_entries(&path)),
+                    }
+                }
+                rom.companions.push(path);
+            }
+            let is_m3u = rom
+                .path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.eq_ignore_ascii_case("m3u"))
+                .unwrap_or(false);
+            if is_m3u {
+                rom.discs = references.get(&rom.path).cloned().unwrap_or_default();
+            }
+            rom
+        })
+        .collect();
+
+    // Group "(Disc N)" files by folder and title
+    let mut disc_sets: HashMap<(PathBuf, String), Vec<(u32, usize)>> = HashMap::new();
+    for (index, rom) in roots.iter().enumerate() {
+        if !rom.discs.is_empty() {
+            continue;
+        }
+        let stem = rom.path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        if let Some((title, number)) = parse_disc_number(stem) {
+            let dir = rom.path.parent().unwrap_or(Path::new("")).to_path_buf();
+            disc_sets
+                .entry((dir, title.to_lowercase()))
+                .or_default()
+                .push((number, index));
+        }
+    }
+
+    let mut merged = HashSet::new();
+    for mut discs in disc_sets.into_values().filter(|d| d.len() > 1) {
+        discs.sort();
+        let (_, first) = discs[0];
+
+        let mut disc_paths = Vec::new();
+        let mut companions = Vec::new();
+        for &(_, index) in &discs {
+            disc_paths.push(roots[index].path.clone());
+            companions.extend(roots[index].companions.iter().cloned());
+            if index != first {
+                companions.push(roots[index].path.clone());
+                merged.insert(index);
+            }
+        }
+
+        // Sort before removing duplicates, as discs can share track files
+        companions.sort();
+        companions.dedup();
+
+        debug!(
+            "Grouped {} discs with {}",
+            disc_paths.len(),
+            roots[first].name
+        );
+        let rom = &mut roots[first];
+        rom.discs = disc_paths;
+        rom.companions = companions;
+    }
+
+    roots
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !merged.contains(index))
+        .map(|(_, rom)| rom)
+        .collect()
+}
+
+/// Get the title shared by a multi-disc game's discs, e.g. "Final Fantasy VII (USA)"
+pub fn disc_set_title(rom: &RomFile) -> String {
+    rom.discs
+        .first()
+        .and_then(|disc| disc.file_stem())
+        .and_then(|stem| stem.to_str())
+        .and_then(parse_disc_number)
+        .map(|(title, _)| title)
+        .unwrap_or_else(|| rom.name_no_extension.clone())
+}
+
+/// Write an .m3u playlist for a multi-disc game and return the game with the playlist as its
+/// path, named after the disc set title.
+///
+/// With `hide_discs`, the disc files are moved into a hidden `.<title>` folder next to the
+/// playlist so that frontends only list the playlist.
+pub fn write_m3u(rom: &RomFile, hide_discs: bool) -> Result<RomFile, std::io::Error> {
+    let title = disc_set_title(rom);
+    let dir = rom.path.parent().unwrap_or(Path::new(""));
+    let m3u_path = dir.join(format!("{}.m3u", title));
+
+    let mut moved = HashMap::new();
+    if hide_discs {
+        let hidden_dir = dir.join(format!(".{}", title));
+        fs::create_dir_all(&hidden_dir)?;
+
+        for file in rom.discs.iter().chain(&rom.companions) {
+            if moved.contains_key(file) || !file.starts_with(dir) || !file.exists() {
+                continue;
+            }
+            let Some(file_name) = file.file_name() else {
+                continue;
+            };
+            let destination = hidden_dir.join(file_name);
+            fs::rename(file, &destination)?;
+            moved.insert(file.clone(), destination);
+        }
+    }
+    let relocate = |path: &PathBuf| moved.get(path).cloned().unwrap_or_else(|| path.clone());
+
+    let discs: Vec<PathBuf> = rom.discs.iter().map(relocate).collect();
+
+    if !m3u_path.exists() {
+        let entries: Vec<String> = discs
+            .iter()
+            .filter_map(|disc| disc.strip_prefix(dir).ok())
+            .map(|disc| disc.to_string_lossy().replace('\\', "/"))
+            .collect();
+        fs::write(&m3u_path, entries.join("\n") + "\n")?;
+        info!("Wrote {}", m3u_path.display());
+    }
+
+    let name = format!("{}.m3u", title);
+    let mut companions = discs.clone();
+    companions.extend(rom.companions.iter().map(relocate));
+    companions.sort();
+    companions.dedup();
+
+    Ok(RomFile {
+        path: m3u_path,
+        relative_path: rom.relative_dir().join(&name),
+        name,
+        name_no_extension: title,
+        console: rom.console.clone(),
+        discs,
+        companions,
+        serial: rom.serial.clone(),
+        parsed_name: rom.parsed_name.clone(),
+        dat_match: rom.dat_match.clone(),
+        arcade_set: rom.arcade_set.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::ConsolesConfig;
+    use crate::scanner::RomScanner;
+
+    use test_case::test_case;
+
+    #[test_case("Final Fantasy VII (USA) (Disc 1)", Some(("Final Fantasy VII (USA)", 1)))]
+    #[test_case("Metal Gear Solid (USA) (Disc 2) (Rev 1)", Some(("Metal Gear Solid (USA) (Rev 1)", 2)))]
+    #[test_case("Riven (Disc 3 of 5)", Some(("Riven", 3)))]
+    #[test_case("Policenauts [CD2]", Some(("Policenauts", 2)))]
+    #[test_case("Crash Bandicoot (USA)", None)]
+    fn test_parse_disc_number(stem: &str, expected: Option<(&str, u32)>) {
+        assert_eq!(
+            parse_disc_number(stem),
+            expected.map(|(title, number)| (title.to_string(), number))
+        );
+    }
+
+    #[test]
+    fn test_group_discs_and_cue_tracks() {
+        let temp_dir = std::env::temp_dir().join("collie_test_group_discs");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let ps_dir = temp_dir.join("PS");
+        std::fs::create_dir_all(&ps_dir).unwrap();
+        for disc in 1..=3 {
+            let stem = format!("Final Fantasy VII (USA) (Disc {})", disc);
+            std::fs::write(
+                ps_dir.join(format!("{}.cue", stem)),
+                format!("FILE \"{}.bin\" BINARY\n  TRACK 01 MODE2/2352\n", stem),
+            )
+            .unwrap();
+            std::fs::write(ps_dir.join(format!("{}.bin", stem)), "").unwrap();
+        }
+        std::fs::write(ps_dir.join("Crash Bandicoot (USA).bin"), "").unwrap();
+
+        let scanner = RomScanner::new(ConsolesConfig::from_embedded().unwrap());
+        let roms = scanner.scan_directory(&temp_dir).unwrap().roms;
+        assert_eq!(roms.len(), 2);
+
+        // Without a playlist the game keeps the first disc's name, as frontends list it
+        let ff7 = roms
+            .iter()
+            .find(|r| r.name == "Final Fantasy VII (USA) (Disc 1).cue")
+            .unwrap();
+        assert_eq!(ff7.name_no_extension, "Final Fantasy VII (USA) (Disc 1)");
+        assert_eq!(ff7.discs.len(), 3);
+        assert!(ff7.discs[2].ends_with("Final Fantasy VII (USA) (Disc 3).cue"));
+        assert_eq!(ff7.companions.len(), 5);
+
+        // Writing the playlist hides the discs and leaves a single game on rescan
+        let ff7 = write_m3u(ff7, true).unwrap();
+        assert_eq!(ff7.name, "Final Fantasy VII (USA).m3u");
+        assert_eq!(ff7.name_no_extension, "Final Fantasy VII (USA)");
+        let m3u = std::fs::read_to_string(&ff7.path).unwrap();
+        assert_eq!(
+            m3u.lines().next(),
+            Some(".Final Fantasy VII (USA)/Final Fantasy VII (USA) (Disc 1).cue")
+        );
+
+        let roms = scanner.scan_directory(&temp_dir).unwrap().roms;
+        assert_eq!(roms.len(), 2);
+        let ff7 = roms
+            .iter()
+            .find(|r| r.name == "Final Fantasy VII (USA).m3u")
+            .unwrap();
+        assert_eq!(ff7.discs.len(), 3);
+        assert_eq!(ff7.companions.len(), 6);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::scanner::RomFile;
+
+/// Game IDs pinned by hand for ROMs that auto-matching gets wrong, stored in
+/// `.collie/overrides.toml`:
+///
+/// ```toml
+/// ["GBA/Metroid Fusion (USA).gba"]
+/// screenscraper = "12345"
+/// thegamesdb = "678"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Overrides {
+    /// Backend game IDs by ROM path relative to the ROMs folder, then by backend name
+    games: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Overrides {
+    /// Get the path of the overrides file
+    pub fn path(roms_path: &Path) -> PathBuf {
+        roms_path.join(".collie").join("overrides.toml")
+    }
+
+    /// Load the overrides file, if there is one
+    pub fn load(roms_path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::path(roms_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let overrides = toml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        Ok(overrides)
+    }
+
+    pub fn save(&self, roms_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path(roms_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Get the key a ROM is pinned by, its path relative to the ROMs folder with forward slashes
+    pub fn rom_key(roms_path: &Path, rom: &RomFile) -> String {
+        rom.path
+            .strip_prefix(roms_path)
+            .unwrap_or(&rom.path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    /// Get the game ID pinned for a ROM on a backend, matching backend names case-insensitively
+    pub fn game_id(&self, rom_key: &str, backend: &str) -> Option<&str> {
+        self.games
+            .get(rom_key)?
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(backend))
+            .map(|(_, id)| id.as_str())
+    }
+
+    /// Get the game IDs pinned for a ROM, by lowercase backend name
+    pub fn game_ids(&self, rom_key: &str) -> BTreeMap<String, String> {
+        self.games
+            .get(rom_key)
+            .into_iter()
+            .flatten()
+            .map(|(name, id)| (name.to_lowercase(), id.clone()))
+            .collect()
+    }
+
+    /// Check whether any game ID is pinned for a ROM
+    pub fn is_pinned(&self, rom_key: &str) -> bool {
+        self.games.contains_key(rom_key)
+    }
+
+    /// Pin a ROM to a game ID on a backend, or unpin it with `None`
+    pub fn set(&mut self, rom_key: &str, backend: &str, game_id: Option<String>) {
+        let ids = self.games.entry(rom_key.to_string()).or_default();
+        ids.retain(|name, _| !name.eq_ignore_ascii_case(backend));
+        if let Some(game_id) = game_id {
+            ids.insert(backend.to_lowercase(), game_id);
+        }
+        if ids.is_empty() {
+            self.games.remove(rom_key);
+        }
+    }
+
+    /// Move the game IDs pinned for a ROM to its new path after a rename
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(ids) = self.games.remove(from) {
+            self.games.insert(to.to_string(), ids);
+        }
+    }
+
+    /// Iterate over pinned ROMs and their game IDs by backend
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &BTreeMap<String, String>)> {
+        self.games.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_edit() {
+        let mut overrides: Overrides = toml::from_str(
+            r#"
+["GBA/Metroid Fusion (USA).gba"]
+screenscraper = "12345"
+
+["SNES/Zelda.sfc"]
+TheGamesDB = "678"
+"#,
+        )
+        .unwrap();
+
+        let key = "GBA/Metroid Fusion (USA).gba";
+        assert_eq!(overrides.game_id(key, "ScreenScraper"), Some("12345"));
+        assert_eq!(overrides.game_id(key, "TheGamesDB"), None);
+        assert_eq!(
+            overrides.game_id("SNES/Zelda.sfc", "TheGamesDB"),
+            Some("678")
+        );
+
+        overrides.set(key, "TheGamesDB", Some("42".to_string()));
+        assert_eq!(overrides.game_id(key, "thegamesdb"), Some("42"));
+        assert_eq!(
+            overrides.game_ids("SNES/Zelda.sfc"),
+            BTreeMap::from([("thegamesdb".to_string(), "678".to_string())])
+        );
+
+        overrides.set("SNES/Zelda.sfc", "thegamesdb", None);
+        assert!(!overrides.is_pinned("SNES/Zelda.sfc"));
+        assert_eq!(overrides.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = std::env::temp_dir().join("collie_test_overrides");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        assert_eq!(Overrides::load(&temp_dir).unwrap(), Overrides::default());
+
+        let mut overrides = Overrides::default();
+        overrides.set(
+            "PS/Final Fantasy VII.m3u",
+            "ScreenScraper",
+            Some("9".into()),
+        );
+        overrides.save(&temp_dir).unwrap();
+
+        let loaded = Overrides::load(&temp_dir).unwrap();
+        assert_eq!(loaded, overrides);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -1,7 +1,10 @@
 use serde::Serialize;
+use std::collections::BTreeMap;
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::info;
 
+use crate::scraper::Quota;
+
 #[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ScrapeStatus {
@@ -47,6 +50,12 @@ pub struct GameMetadata {
     pub rating: Option<String>,
     pub image_path: Option<String>,
     pub error_message: Option<String>,
+    /// Match confidence reported by the scraper, from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    /// Game IDs pinned for the ROM when it was scraped, by lowercase backend name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pinned_ids: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -62,6 +71,8 @@ pub struct ScrapingProgress {
     pub success_count: usize,
     pub fail_count: usize,
     pub skip_count: usize,
+    /// Remaining quota by scraper name, for scrapers that report one
+    pub quotas: BTreeMap<String, Quota>,
 }
 
 #[derive(Debug, Clone, Serialize, serde::Deserialize)]
@@ -74,6 +85,8 @@ pub struct ProgressUpdate {
     pub skip_count: usize,
     pub message: String,
     pub game_update: Option<GameData>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub quotas: BTreeMap<String, Quota>,
 }
 
 /// Helper function to send progress update
@@ -106,5 +119,6 @@ pub fn send_progress(
         skip_count: progress.skip_count,
         message,
         game_update,
+        quotas: progress.quotas.clone(),
     });
 }
@@ -0,0 +1,861 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::console::ConsolesConfig;
+use crate::dat::DatIndex;
+use crate::filename::ParsedName;
+use crate::hashing::HashIndex;
+use crate::multidisc;
+use crate::overrides::Overrides;
+use crate::progress::GameData;
+use crate::scanner::{RomFile, RomScanner};
+use crate::scraper::media::MediaKind;
+use crate::scraping::{MediaLayout, media_dir, media_path};
+use crate::storage::{game_data_path, load_game_data};
+
+/// Default naming template, the canonical name as is
+pub const DEFAULT_TEMPLATE: &str = "{name}";
+
+pub struct RenameConfig {
+    pub roms_path: PathBuf,
+    /// User console definitions, defaults to `.collie/consoles.toml` in the ROMs folder
+    pub consoles_path: Option<PathBuf>,
+    pub images_folder: String,
+    pub guides_folder: String,
+    pub max_scan_depth: usize,
+    pub media_layout: MediaLayout,
+    /// New file name without extension, using `{name}`, `{title}`, `{region}` and `{console}`
+    pub template: String,
+    /// Only report what would be renamed
+    pub dry_run: bool,
+    pub hash_index: Arc<HashIndex>,
+}
+
+/// A file or folder moved by a rename
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// The files renamed for one game
+#[derive(Debug, Clone, Serialize)]
+pub struct GameRename {
+    pub from: String,
+    pub to: String,
+    pub files: Vec<FileMove>,
+}
+
+/// A game left alone, and why
+#[derive(Debug, Clone, Serialize)]
+pub struct RenameSkip {
+    pub rom: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameReport {
+    pub dry_run: bool,
+    pub renamed: Vec<GameRename>,
+    pub skipped: Vec<RenameSkip>,
+    /// Journal that undoes this batch, if anything was renamed
+    pub journal: Option<PathBuf>,
+}
+
+/// A step of a rename batch, recorded so that it can be undone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum JournalEntry {
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// File contents were rewritten; `original` is what to restore
+    Rewrite {
+        path: PathBuf,
+        original: String,
+    },
+}
+
+/// Append-only record of a rename batch in `.collie/renames/`
+struct Journal {
+    path: PathBuf,
+    file: File,
+    /// Steps recorded so far, with the journal's length before each
+    steps: Vec<(u64, JournalEntry)>,
+}
+
+impl Journal {
+    fn create(roms_path: &Path) -> Result<Self, std::io::Error> {
+        let dir = journal_dir(roms_path);
+        fs::create_dir_all(&dir)?;
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let path = dir.join(format!("{:020}.jsonl", millis));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            file,
+            steps: Vec::new(),
+        })
+    }
+
+    fn record(&mut self, entry: &JournalEntry) -> Result<(), Box<dyn std::error::Error>> {
+        let len = self.file.metadata()?.len();
+        self.steps.push((len, entry.clone()));
+        writeln!(self.file, "{}", serde_json::to_string(entry)?)?;
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// Undo the steps recorded since the journal had `mark` steps and drop them from it
+    fn roll_back(&mut self, mark: usize) -> Result<(), Box<dyn std::error::Error>> {
+        while self.steps.len() > mark {
+            let Some((len, entry)) = self.steps.pop() else {
+                break;
+            };
+            undo(&entry)?;
+            self.file.set_len(len)?;
+        }
+        Ok(())
+    }
+}
+
+/// Revert a journaled step
+fn undo(entry: &JournalEntry) -> Result<(), std::io::Error> {
+    match entry {
+        JournalEntry::Move { from, to } => {
+            // A failed move is journaled before it is made
+            if to.exists() && !from.exists() {
+                fs::rename(to, from)?;
+            }
+        }
+        JournalEntry::Rewrite { path, original } => fs::write(path, original)?,
+    }
+    Ok(())
+}
+
+fn journal_dir(roms_path: &Path) -> PathBuf {
+    roms_path.join(".collie").join("renames")
+}
+
+/// Render a naming template for a game's canonical name, e.g. "{title} ({region})".
+///
+/// Empty brackets left by missing values are dropped and characters not allowed in
+/// file names are removed.
+pub fn render_template(template: &str, name: &str, fallback: &ParsedName, console: &str) -> String {
+    let parsed = ParsedName::parse(name);
+    let regions = if parsed.regions.is_empty() {
+        &fallback.regions
+    } else {
+        &parsed.regions
+    };
+
+    let rendered = template
+        .replace("{name}", name)
+        .replace("{title}", &parsed.title)
+        .replace("{region}", &regions.join(", "))
+        .replace("{console}", console)
+        .replace("()", "")
+        .replace("[]", "")
+        .replace(": ", " - ")
+        .replace(['\\', '/', ':', '*', '?', '"', '<', '>', '|'], "");
+
+    rendered
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Get the name a ROM is known by, from a DAT, an arcade list or a previous scrape
+fn canonical_name(roms_path: &Path, rom: &RomFile) -> Option<String> {
+    let name = rom
+        .canonical_name()
+        .map(String::from)
+        .or_else(|| load_game_data(roms_path, rom)?.metadata.name)?;
+
+    // Disc sets are named after the set, not the first disc
+    if rom.discs.len() > 1 {
+        return multidisc::parse_disc_number(&name)
+            .map(|(title, _)| title)
+            .or(Some(name));
+    }
+    Some(name)
+}
+
+/// Pair the stems a game's files are named after with their new stems.
+///
+/// A disc set without a playlist is listed by its first disc, so every disc keeps its own
+/// stem, renamed to `new_stem` plus its "(Disc N)" tag. The ROM's own stem comes first.
+fn renamed_stems(rom: &RomFile, new_stem: &str) -> Vec<(String, String)> {
+    if rom.discs.len() < 2 || !rom.discs.contains(&rom.path) {
+        return vec![(rom.name_no_extension.clone(), new_stem.to_string())];
+    }
+
+    let mut stems = vec![];
+    for disc in &rom.discs {
+        let Some(stem) = disc.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let Some((_, number)) = multidisc::parse_disc_number(stem) else {
+            continue;
+        };
+        let renamed = (stem.to_string(), format!("{} (Disc {})", new_stem, number));
+        if disc == &rom.path {
+            stems.insert(0, renamed);
+        } else {
+            stems.push(renamed);
+        }
+    }
+    stems
+}
+
+/// Work out the files to move to rename a game to `new_stem`: the ROM and its companions,
+/// its images and other media, its guides folder and its game data
+fn plan_game(
+    roms_path: &Path,
+    rom: &RomFile,
+    new_stem: &str,
+    config: &RenameConfig,
+) -> Result<Vec<FileMove>, String> {
+    let stems = renamed_stems(rom, new_stem);
+    let old_stem = rom.name_no_extension.as_str();
+    let new_stem = stems
+        .iter()
+        .find(|(old, _)| old == old_stem)
+        .map(|(_, new)| new.as_str())
+        .unwrap_or(new_stem);
+    let mut moves = Vec::new();
+
+    // Discs and tracks named after the game keep their suffix, e.g. " (Track 2).bin"
+    let mut files = vec![rom.path.clone()];
+    for companion in &rom.companions {
+        if !files.contains(companion) {
+            files.push(companion.clone());
+        }
+    }
+    for file in files {
+        let Some(file_name) = file.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        // The longest stem wins, so "(Disc 10)" is not taken for "(Disc 1)"
+        let Some((suffix, renamed)) = stems
+            .iter()
+            .filter_map(|(old, new)| Some((file_name.strip_prefix(old.as_str())?, new)))
+            .min_by_key(|(suffix, _)| suffix.len())
+        else {
+            continue;
+        };
+        let to = file.with_file_name(format!("{}{}", renamed, suffix));
+        moves.push(FileMove { from: file, to });
+    }
+
+    let image = media_dir(rom, &config.images_folder, config.media_layout)
+        .join(format!("{}.png", old_stem));
+    if image.exists() {
+        let to = image.with_file_name(format!("{}.png", new_stem));
+        moves.push(FileMove { from: image, to });
+    }
+
+    for kind in MediaKind::ALL {
+        let media = media_path(rom, &config.images_folder, config.media_layout, kind);
+        if media.exists() {
+            let to = media.with_file_name(format!("{}.{}", new_stem, kind.extension()));
+            moves.push(FileMove { from: media, to });
+        }
+    }
+
+    let guides = media_dir(rom, &config.guides_folder, config.media_layout).join(old_stem);
+    if guides.is_dir() {
+        let to = guides.with_file_name(new_stem);
+        moves.push(FileMove { from: guides, to });
+    }
+
+    let game_data = game_data_path(roms_path, rom);
+    if game_data.exists() {
+        let renamed = RomFile {
+            name_no_extension: new_stem.to_string(),
+            ..rom.clone()
+        };
+        moves.push(FileMove {
+            from: game_data,
+            to: game_data_path(roms_path, &renamed),
+        });
+    }
+
+    if let Some(existing) = moves.iter().find(|m| m.to.exists()) {
+        return Err(format!("{} already exists", existing.to.display()));
+    }
+    Ok(moves)
+}
+
+/// Check whether a file is a .cue sheet or .m3u playlist
+fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("cue") || e.eq_ignore_ascii_case("m3u"))
+        .unwrap_or(false)
+}
+
+/// Rewrite a file's contents, recording the original in the journal
+fn rewrite(
+    journal: &mut Journal,
+    path: &Path,
+    update: impl FnOnce(&str) -> Result<String, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let original = fs::read_to_string(path)?;
+    let updated = update(&original)?;
+    if updated != original {
+        journal.record(&JournalEntry::Rewrite {
+            path: path.to_path_buf(),
+            original,
+        })?;
+        fs::write(path, updated)?;
+    }
+    Ok(())
+}
+
+/// Move a game's files and update the playlists and game data that refer to them.
+///
+/// A game is renamed whole or not at all: on failure, its steps are undone and dropped
+/// from the journal.
+fn apply_game(
+    journal: &mut Journal,
+    roms_path: &Path,
+    rom: &RomFile,
+    new_name: &str,
+    moves: &[FileMove],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mark = journal.steps.len();
+    let result = move_game(journal, roms_path, rom, new_name, moves);
+    if result.is_err()
+        && let Err(e) = journal.roll_back(mark)
+    {
+        warn!("Failed to roll back {}: {}", rom.name, e);
+    }
+    result
+}
+
+fn move_game(
+    journal: &mut Journal,
+    roms_path: &Path,
+    rom: &RomFile,
+    new_name: &str,
+    moves: &[FileMove],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for file_move in moves {
+        journal.record(&JournalEntry::Move {
+            from: file_move.from.clone(),
+            to: file_move.to.clone(),
+        })?;
+        fs::rename(&file_move.from, &file_move.to)?;
+    }
+
+    let renamed_files: Vec<(String, String)> = moves
+        .iter()
+        .filter_map(|m| {
+            let from = m.from.file_name()?.to_str()?;
+            let to = m.to.file_name()?.to_str()?;
+            Some((from.to_string(), to.to_string()))
+        })
+        .collect();
+
+    for file_move in moves.iter().filter(|m| is_playlist(&m.to)) {
+        rewrite(journal, &file_move.to, |content| {
+            Ok(content
+                .lines()
+                .map(|line| {
+                    renamed_files
+                        .iter()
+                        .fold(line.to_string(), |line, (from, to)| line.replace(from, to))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+                + "\n")
+        })?;
+    }
+
+    let game_data = game_data_path(roms_path, rom);
+    if let Some(file_move) = moves.iter().find(|m| m.from == game_data) {
+        rewrite(journal, &file_move.to, |content| {
+            let mut data: GameData = serde_json::from_str(content)?;
+            data.rom_name = new_name.to_string();
+            data.metadata.image_path = data.metadata.image_path.map(|path| {
+                renamed_files
+                    .iter()
+                    .fold(path, |path, (from, to)| path.replace(from, to))
+            });
+            Ok(serde_json::to_string_pretty(&data)?)
+        })?;
+    }
+
+    // Keep game IDs pinned to the ROM's old path
+    let overrides_path = Overrides::path(roms_path);
+    let old_key = Overrides::rom_key(roms_path, rom);
+    if overrides_path.exists()
+        && let Some(rom_move) = moves.iter().find(|m| m.from == rom.path)
+    {
+        let renamed = RomFile {
+            path: rom_move.to.clone(),
+            ..rom.clone()
+        };
+        let new_key = Overrides::rom_key(roms_path, &renamed);
+        rewrite(journal, &overrides_path, |content| {
+            let mut overrides: Overrides = toml::from_str(content)?;
+            if !overrides.is_pinned(&old_key) {
+                return Ok(content.to_string());
+            }
+            overrides.rename(&old_key, &new_key);
+            Ok(toml::to_string(&overrides)?)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Rename identified ROMs and their media to the naming template.
+///
+/// ROMs are identified by imported DATs or by their previously scraped name. Every change
+/// is journaled in `.collie/renames/` for [`undo_last_rename`].
+pub async fn rename_roms(
+    config: &RenameConfig,
+) -> Result<RenameReport, Box<dyn std::error::Error + Send + Sync>> {
+    let roms_path = config.roms_path.as_path();
+
+    let (consoles_config, issues) =
+        ConsolesConfig::load(roms_path, config.consoles_path.as_deref())
+            .map_err(|e| format!("Failed to load console config: {}", e))?;
+    for issue in issues {
+        warn!("Console config: {}", issue);
+    }
+
+    let scanner = RomScanner::new(consoles_config)
+        .with_max_depth(config.max_scan_depth)
+        .with_ignored_folders(vec![
+            config.images_folder.clone(),
+            config.guides_folder.clone(),
+        ]);
+    let roms = scanner
+        .scan_directory(roms_path)
+        .map_err(|e| format!("Failed to scan ROMs: {}", e))?
+        .roms;
+
+    let dat_index = DatIndex::load(roms_path);
+
+    let mut report = RenameReport {
+        dry_run: config.dry_run,
+        ..Default::default()
+    };
+    let mut journal = None;
+
+    for mut rom in roms {
+        let rom_label = rom.relative_path.display().to_string();
+        let skip = |reason: &str| RenameSkip {
+            rom: rom_label.clone(),
+            reason: reason.to_string(),
+        };
+
+        // MAME and FBNeo look romsets up by their short name
+        if rom.console.arcade {
+            report
+                .skipped
+                .push(skip("Arcade romsets must keep their set name"));
+            continue;
+        }
+
+        if !dat_index.is_empty() {
+            rom.dat_match = dat_index.identify(&rom, &config.hash_index).await;
+        }
+        let Some(name) = canonical_name(roms_path, &rom) else {
+            report.skipped.push(skip("Not identified"));
+            continue;
+        };
+        let new_stem =
+            render_template(&config.template, &name, &rom.parsed_name, &rom.console.name);
+        if new_stem.is_empty() {
+            report.skipped.push(skip("Template gave an empty name"));
+            continue;
+        }
+        if renamed_stems(&rom, &new_stem)
+            .iter()
+            .all(|(old, new)| old == new)
+        {
+            continue;
+        }
+
+        let moves = match plan_game(roms_path, &rom, &new_stem, config) {
+            Ok(moves) => moves,
+            Err(reason) => {
+                report.skipped.push(skip(&reason));
+                continue;
+            }
+        };
+        let new_name = moves
+            .first()
+            .and_then(|m| m.to.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| rom.name.clone());
+
+        if !config.dry_run {
+            let journal = match &mut journal {
+                Some(journal) => journal,
+                None => journal.insert(Journal::create(roms_path)?),
+            };
+            info!("Renaming {} to {}", rom.name, new_name);
+            if let Err(e) = apply_game(journal, roms_path, &rom, &new_name, &moves) {
+                warn!("Failed to rename {}: {}", rom.name, e);
+                report.skipped.push(skip(&e.to_string()));
+                continue;
+            }
+        }
+
+        report.renamed.push(GameRename {
+            from: rom.name.clone(),
+            to: new_name,
+            files: moves,
+        });
+    }
+
+    // Games that failed were rolled back, possibly leaving nothing to undo
+    report.journal = match journal {
+        Some(journal) if journal.steps.is_empty() => {
+            fs::remove_file(&journal.path)?;
+            None
+        }
+        journal => journal.map(|journal| journal.path),
+    };
+    Ok(report)
+}
+
+/// Undo the most recent rename batch and delete its journal.
+///
+/// Returns the number of steps undone, or `None` if there is nothing to undo.
+pub fn undo_last_rename(roms_path: &Path) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+    let Ok(dir) = fs::read_dir(journal_dir(roms_path)) else {
+        return Ok(None);
+    };
+    let Some(journal_path) = dir
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+        .max()
+    else {
+        return Ok(None);
+    };
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(File::open(&journal_path)?).lines() {
+        entries.push(serde_json::from_str::<JournalEntry>(&line?)?);
+    }
+
+    for entry in entries.iter().rev() {
+        undo(entry)?;
+    }
+
+    fs::remove_file(&journal_path)?;
+    info!("Undid {} rename steps", entries.len());
+    Ok(Some(entries.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{GameGuides, GameMetadata, ScrapeStatus};
+    use crate::storage::save_game_data;
+
+    use test_case::test_case;
+
+    #[test_case(
+        "{name}",
+        "Legend of Zelda, The - Minish Cap (USA)",
+        "Legend of Zelda, The - Minish Cap (USA)"
+    )]
+    #[test_case(
+        "{title} ({region})",
+        "Legend of Zelda, The - Minish Cap (USA)",
+        "The Legend of Zelda - Minish Cap (USA)"
+    )]
+    #[test_case(
+        "{title} ({region})",
+        "Street Fighter II: The World Warrior",
+        "Street Fighter II - The World Warrior (Europe)"
+    )]
+    #[test_case("{console} - {title} [{region}]", "Metroid", "GBA - Metroid [Europe]")]
+    fn test_render_template(template: &str, name: &str, expected: &str) {
+        let fallback = ParsedName::parse("sf2 (Europe)");
+        assert_eq!(render_template(template, name, &fallback, "GBA"), expected);
+    }
+
+    #[test]
+    fn test_render_template_drops_empty_brackets() {
+        let fallback = ParsedName::default();
+        assert_eq!(
+            render_template("{title} ({region})", "Metroid", &fallback, "NES"),
+            "Metroid"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_undo() {
+        let temp_dir = std::env::temp_dir().join("collie_test_rename");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let ps_dir = temp_dir.join("PS");
+        std::fs::create_dir_all(ps_dir.join("Imgs")).unwrap();
+
+        std::fs::write(ps_dir.join("ff7.cue"), "FILE \"ff7.bin\" BINARY\n").unwrap();
+        std::fs::write(ps_dir.join("ff7.bin"), "").unwrap();
+        std::fs::write(ps_dir.join("Imgs").join("ff7.png"), "").unwrap();
+        std::fs::create_dir_all(ps_dir.join("Imgs").join("videos")).unwrap();
+        std::fs::write(ps_dir.join("Imgs").join("videos").join("ff7.mp4"), "").unwrap();
+        std::fs::write(ps_dir.join("unknown.bin"), "").unwrap();
+
+        let config = RenameConfig {
+            roms_path: temp_dir.clone(),
+            consoles_path: None,
+            images_folder: "Imgs".to_string(),
+            guides_folder: "Guides".to_string(),
+            max_scan_depth: crate::scanner::DEFAULT_MAX_DEPTH,
+            media_layout: MediaLayout::Mirrored,
+            template: "{title} ({region})".to_string(),
+            dry_run: true,
+            hash_index: Arc::new(HashIndex::open(&temp_dir)),
+        };
+
+        // Identify the game through a previous scrape
+        let scanner = RomScanner::new(ConsolesConfig::from_embedded().unwrap());
+        let roms = scanner.scan_directory(&temp_dir).unwrap().roms;
+        let ff7 = roms.iter().find(|r| r.name == "ff7.cue").unwrap();
+        let game_data = GameData {
+            rom_name: ff7.name.clone(),
+            metadata: GameMetadata {
+                status: ScrapeStatus::Success,
+                name: Some("Final Fantasy VII (USA)".to_string()),
+                developer: None,
+                publisher: None,
+                genre: None,
+                release_date: None,
+                rating: None,
+                image_path: Some("/api/images/PS/Imgs/ff7.png".to_string()),
+                error_message: None,
+                confidence: None,
+                pinned_ids: Default::default(),
+            },
+            guides: GameGuides {
+                status: ScrapeStatus::Skipped,
+                count: None,
+            },
+        };
+        save_game_data(&temp_dir, ff7, &game_data).unwrap();
+        let mut overrides = Overrides::default();
+        overrides.set("PS/ff7.cue", "ScreenScraper", Some("1".to_string()));
+        overrides.save(&temp_dir).unwrap();
+
+        // A dry run only reports
+        let report = rename_roms(&config).await.unwrap();
+        assert_eq!(report.renamed.len(), 1);
+        assert_eq!(report.renamed[0].to, "Final Fantasy VII (USA).cue");
+        assert_eq!(report.renamed[0].files.len(), 5);
+        assert_eq!(report.skipped.len(), 1);
+        assert!(report.journal.is_none());
+        assert!(ps_dir.join("ff7.cue").exists());
+
+        let config = RenameConfig {
+            dry_run: false,
+            ..config
+        };
+        let report = rename_roms(&config).await.unwrap();
+        assert!(report.journal.is_some());
+        assert!(!ps_dir.join("ff7.bin").exists());
+        assert_eq!(
+            std::fs::read_to_string(ps_dir.join("Final Fantasy VII (USA).cue")).unwrap(),
+            "FILE \"Final Fantasy VII (USA).bin\" BINARY\n"
+        );
+        assert!(
+            ps_dir
+                .join("Imgs")
+                .join("Final Fantasy VII (USA).png")
+                .exists()
+        );
+        assert!(
+            ps_dir
+                .join("Imgs")
+                .join("videos")
+                .join("Final Fantasy VII (USA).mp4")
+                .exists()
+        );
+
+        let roms = scanner.scan_directory(&temp_dir).unwrap().roms;
+        let renamed = roms.iter().find(|r| r.name.starts_with("Final")).unwrap();
+        let saved = load_game_data(&temp_dir, renamed).unwrap();
+        assert_eq!(saved.rom_name, "Final Fantasy VII (USA).cue");
+        assert_eq!(
+            saved.metadata.image_path.as_deref(),
+            Some("/api/images/PS/Imgs/Final Fantasy VII (USA).png")
+        );
+
+        let overrides = Overrides::load(&temp_dir).unwrap();
+        assert!(overrides.is_pinned("PS/Final Fantasy VII (USA).cue"));
+
+        // Undo restores every file and the journal is consumed
+        assert_eq!(undo_last_rename(&temp_dir).unwrap(), Some(8));
+        assert!(Overrides::load(&temp_dir).unwrap().is_pinned("PS/ff7.cue"));
+        assert_eq!(
+            std::fs::read_to_string(ps_dir.join("ff7.cue")).unwrap(),
+            "FILE \"ff7.bin\" BINARY\n"
+        );
+        assert!(ps_dir.join("ff7.bin").exists());
+        assert!(ps_dir.join("Imgs").join("ff7.png").exists());
+        assert_eq!(load_game_data(&temp_dir, ff7).unwrap().rom_name, "ff7.cue");
+        assert_eq!(undo_last_rename(&temp_dir).unwrap(), None);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_rename_disc_set_without_playlist() {
+        let temp_dir = std::env::temp_dir().join("collie_test_rename_discs");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let ps_dir = temp_dir.join("PS");
+        std::fs::create_dir_all(ps_dir.join("Imgs")).unwrap();
+
+        for disc in 1..=3 {
+            let stem = format!("ff7 (Disc {})", disc);
+            std::fs::write(
+                ps_dir.join(format!("{}.cue", stem)),
+                format!("FILE \"{}.bin\" BINARY\n", stem),
+            )
+            .unwrap();
+            std::fs::write(ps_dir.join(format!("{}.bin", stem)), "").unwrap();
+        }
+        std::fs::write(ps_dir.join("Imgs").join("ff7 (Disc 1).png"), "").unwrap();
+
+        let scanner = RomScanner::new(ConsolesConfig::from_embedded().unwrap());
+        let roms = scanner.scan_directory(&temp_dir).unwrap().roms;
+        assert_eq!(roms.len(), 1);
+        let game_data = GameData {
+            rom_name: roms[0].name.clone(),
+            metadata: GameMetadata {
+                status: ScrapeStatus::Success,
+                name: Some("Final Fantasy VII (USA) (Disc 1)".to_string()),
+                developer: None,
+                publisher: None,
+                genre: None,
+                release_date: None,
+                rating: None,
+                image_path: None,
+                error_message: None,
+                confidence: None,
+                pinned_ids: Default::default(),
+            },
+            guides: GameGuides {
+                status: ScrapeStatus::Skipped,
+                count: None,
+            },
+        };
+        save_game_data(&temp_dir, &roms[0], &game_data).unwrap();
+
+        let config = RenameConfig {
+            roms_path: temp_dir.clone(),
+            consoles_path: None,
+            images_folder: "Imgs".to_string(),
+            guides_folder: "Guides".to_string(),
+            max_scan_depth: crate::scanner::DEFAULT_MAX_DEPTH,
+            media_layout: MediaLayout::Mirrored,
+            template: "{name}".to_string(),
+            dry_run: false,
+            hash_index: Arc::new(HashIndex::open(&temp_dir)),
+        };
+        let report = rename_roms(&config).await.unwrap();
+        assert_eq!(report.renamed.len(), 1);
+        assert_eq!(report.renamed[0].to, "Final Fantasy VII (USA) (Disc 1).cue");
+
+        // Every disc keeps its tag and its sheet points at its renamed track
+        for disc in 1..=3 {
+            let stem = format!("Final Fantasy VII (USA) (Disc {})", disc);
+            assert_eq!(
+                std::fs::read_to_string(ps_dir.join(format!("{}.cue", stem))).unwrap(),
+                format!("FILE \"{}.bin\" BINARY\n", stem)
+            );
+            assert!(ps_dir.join(format!("{}.bin", stem)).exists());
+        }
+        assert!(
+            ps_dir
+                .join("Imgs")
+                .join("Final Fantasy VII (USA) (Disc 1).png")
+                .exists()
+        );
+
+        // The discs are still grouped into one game with its game data
+        let roms = scanner.scan_directory(&temp_dir).unwrap().roms;
+        assert_eq!(roms.len(), 1);
+        assert_eq!(roms[0].discs.len(), 3);
+        assert_eq!(
+            load_game_data(&temp_dir, &roms[0]).unwrap().rom_name,
+            "Final Fantasy VII (USA) (Disc 1).cue"
+        );
+
+        // Renaming again changes nothing
+        let report = rename_roms(&config).await.unwrap();
+        assert!(report.renamed.is_empty());
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_failed_rename_rolls_back() {
+        let temp_dir = std::env::temp_dir().join("collie_test_rename_rollback");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let gba_dir = temp_dir.join("GBA");
+        std::fs::create_dir_all(&gba_dir).unwrap();
+        std::fs::write(gba_dir.join("metroid.gba"), "").unwrap();
+
+        let consoles = ConsolesConfig::from_embedded().unwrap();
+        let console = consoles.find_console("GBA").unwrap().clone();
+        let rom = RomFile::new(gba_dir.join("metroid.gba"), console);
+
+        // The second move fails as its file is gone
+        let moves = vec![
+            FileMove {
+                from: gba_dir.join("metroid.gba"),
+                to: gba_dir.join("Metroid Fusion (USA).gba"),
+            },
+            FileMove {
+                from: gba_dir.join("missing.sav"),
+                to: gba_dir.join("Metroid Fusion (USA).sav"),
+            },
+        ];
+        let mut journal = Journal::create(&temp_dir).unwrap();
+        assert!(
+            apply_game(
+                &mut journal,
+                &temp_dir,
+                &rom,
+                "Metroid Fusion (USA).gba",
+                &moves
+            )
+            .is_err()
+        );
+
+        // The first move is undone and nothing is left in the journal
+        assert!(gba_dir.join("metroid.gba").exists());
+        assert!(!gba_dir.join("Metroid Fusion (USA).gba").exists());
+        assert!(journal.steps.is_empty());
+        assert_eq!(std::fs::read_to_string(&journal.path).unwrap(), "");
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -1,29 +1,185 @@
-use tracing::info;
+use tracing::{debug, info};
 
+use crate::arcade::ArcadeSet;
 use crate::console::{Console, ConsolesConfig};
+use crate::dat::DatEntry;
+use crate::detect;
+use crate::disc;
+use crate::exclude::{ExcludeRule, ExcludeRules};
+use crate::filename::ParsedName;
+use crate::multidisc;
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Default number of subfolder levels scanned below each console folder
+pub const DEFAULT_MAX_DEPTH: usize = 3;
+
 #[derive(Debug, Clone)]
 pub struct RomFile {
     pub path: PathBuf,
+    /// Path of the ROM relative to its console folder, e.g. `RPG/Golden Sun.gba`
+    pub relative_path: PathBuf,
     pub name: String,
+    /// Base name used for images, guides and game data (the set title for m3u playlists)
     pub name_no_extension: String,
     pub console: Console,
+    /// Disc entry files in order, for multi-disc games
+    pub discs: Vec<PathBuf>,
+    /// Other files belonging to this game, such as tracks referenced by a .cue or other discs
+    pub companions: Vec<PathBuf>,
+    /// Product serial read from a disc image's boot data, e.g. `SLUS-00594`
+    pub serial: Option<String>,
+    /// Clean title and tags parsed from the file name
+    pub parsed_name: ParsedName,
+    /// Entry matched by checksum in an imported DAT file
+    pub dat_match: Option<DatEntry>,
+    /// Romset matched by short name in an imported MAME or FBNeo list
+    pub arcade_set: Option<ArcadeSet>,
+}
+
+impl RomFile {
+    /// Create a ROM found directly in its console folder, parsing its file name
+    pub fn new(path: PathBuf, console: Console) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let name_no_extension = path
+            .file_stem()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let parsed_name = ParsedName::parse(&name_no_extension);
+
+        Self {
+            relative_path: PathBuf::from(&name),
+            path,
+            name,
+            name_no_extension,
+            console,
+            discs: Vec::new(),
+            companions: Vec::new(),
+            serial: None,
+            parsed_name,
+            dat_match: None,
+            arcade_set: None,
+        }
+    }
+
+    /// Get the console folder this ROM was found in
+    pub fn console_dir(&self) -> &Path {
+        self.relative_path
+            .components()
+            .fold(self.path.as_path(), |dir, _| dir.parent().unwrap_or(dir))
+    }
+
+    /// Get the canonical name from a DAT or arcade list match, a better search term than the
+    /// file name
+    pub fn canonical_name(&self) -> Option<&str> {
+        self.dat_match
+            .as_ref()
+            .map(|dat_match| dat_match.game.as_str())
+            .or_else(|| self.arcade_set.as_ref().map(|set| set.description.as_str()))
+    }
+
+    /// Get the subfolder of the ROM relative to its console folder (empty for top-level ROMs)
+    pub fn relative_dir(&self) -> &Path {
+        self.relative_path.parent().unwrap_or(Path::new(""))
+    }
+}
+
+/// Why a file in a console folder was not treated as a ROM
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// The extension is not in the console's `extensions` list
+    UnsupportedExtension(Option<String>),
+    /// The file is in a folder that matches no console and its header was not recognised
+    UnknownConsole,
+    /// The file or folder matches a `.collieignore` rule
+    Excluded(ExcludeRule),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::UnsupportedExtension(Some(ext)) => {
+                write!(f, "unsupported extension .{}", ext)
+            }
+            SkipReason::UnsupportedExtension(None) => write!(f, "no file extension"),
+            SkipReason::UnknownConsole => write!(f, "unknown console"),
+            SkipReason::Excluded(rule) => write!(f, "excluded by {}", rule),
+        }
+    }
+}
+
+/// A file that was found while scanning but not treated as a ROM
+#[derive(Debug, Clone)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// Result of scanning a ROMs directory
+#[derive(Debug, Default)]
+pub struct ScanSummary {
+    pub roms: Vec<RomFile>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+impl ScanSummary {
+    /// Get the paths excluded by `.collieignore` rules, with the rule that matched each
+    pub fn excluded(&self) -> impl Iterator<Item = (&Path, &ExcludeRule)> {
+        self.skipped
+            .iter()
+            .filter_map(|skipped| match &skipped.reason {
+                SkipReason::Excluded(rule) => Some((skipped.path.as_path(), rule)),
+                _ => None,
+            })
+    }
 }
 
 pub struct RomScanner {
     consoles_config: ConsolesConfig,
+    max_depth: usize,
+    ignored_folders: Vec<String>,
+    detect_unknown_folders: bool,
 }
 
 impl RomScanner {
     pub fn new(consoles_config: ConsolesConfig) -> Self {
-        Self { consoles_config }
+        Self {
+            consoles_config,
+            max_depth: DEFAULT_MAX_DEPTH,
+            ignored_folders: vec!["Imgs".to_string(), "Guides".to_string()],
+            detect_unknown_folders: false,
+        }
     }
 
-    /// Scan a directory for ROM files
-    pub fn scan_directory(&self, roms_path: &Path) -> Result<Vec<RomFile>, std::io::Error> {
-        let mut rom_files = Vec::new();
+    /// Set how many levels of subfolders are scanned below each console folder
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set folder names that are never scanned for ROMs (e.g. images and guides)
+    pub fn with_ignored_folders(mut self, ignored_folders: Vec<String>) -> Self {
+        self.ignored_folders = ignored_folders;
+        self
+    }
+
+    /// Look for loose ROMs in folders that match no console, identifying each file by its
+    /// name or header. Off by default, as it reads every file in BIOS, save and media folders.
+    pub fn with_detect_unknown_folders(mut self, detect_unknown_folders: bool) -> Self {
+        self.detect_unknown_folders = detect_unknown_folders;
+        self
+    }
+
+    /// Scan a directory for ROM files.
+    ///
+    /// Paths matching a `.collieignore` in the ROMs folder or a console folder are skipped.
+    pub fn scan_directory(&self, roms_path: &Path) -> Result<ScanSummary, std::io::Error> {
+        let mut summary = ScanSummary::default();
+        let root_rules = ExcludeRules::load(roms_path);
 
         // Read all directories in the ROMs path
         for entry in fs::read_dir(roms_path)? {
@@ -40,77 +196,315 @@ impl RomScanner {
                 .unwrap_or("")
                 .to_string();
 
+            if let Some(rule) = root_rules.matched(&path, true) {
+                let reason = SkipReason::Excluded(rule);
+                debug!("Skipping {}: {}", path.display(), reason);
+                summary.skipped.push(SkippedFile { path, reason });
+                continue;
+            }
+            let rules = root_rules.with_folder(&path);
+
             // Check if this folder matches a known console
             if let Some(console) = self.consoles_config.find_console(&folder_name) {
                 info!("Scanning {} ({})...", folder_name, console.name);
 
                 // Scan ROMs in this console folder
-                let console_roms = self.scan_console_folder(&path, console)?;
-                rom_files.extend(console_roms);
+                self.scan_console_folder(&path, &path, 0, Some(console), &rules, &mut summary)?;
+            } else if self.detect_unknown_folders
+                && !folder_name.starts_with('.')
+                && !self.ignored_folders.contains(&folder_name)
+            {
+                debug!("Scanning {} for loose ROMs...", folder_name);
+
+                // Identify each file by name or header instead
+                self.scan_console_folder(&path, &path, 0, None, &rules, &mut summary)?;
             }
         }
 
-        Ok(rom_files)
+        summary.roms = multidisc::group_discs(summary.roms);
+
+        // Read serials from disc images, which identify a disc without hashing it
+        for rom in summary
+            .roms
+            .iter_mut()
+            .filter(|r| disc::is_disc_image(&r.path))
+        {
+            rom.serial = disc::read_serial(&rom.path).unwrap_or_else(|e| {
+                debug!("Failed to read serial from {}: {}", rom.path.display(), e);
+                None
+            });
+        }
+
+        Ok(summary)
     }
 
-    /// Scan a specific console folder for ROM files
+    /// Scan a console folder (or one of its subfolders) for ROM files.
+    ///
+    /// Without a console, each file is identified by its name or header.
     fn scan_console_folder(
         &self,
         console_path: &Path,
-        console: &Console,
-    ) -> Result<Vec<RomFile>, std::io::Error> {
-        let mut rom_files = Vec::new();
-
-        for entry in fs::read_dir(console_path)? {
+        folder_path: &Path,
+        depth: usize,
+        console: Option<&Console>,
+        rules: &ExcludeRules,
+        summary: &mut ScanSummary,
+    ) -> Result<(), std::io::Error> {
+        for entry in fs::read_dir(folder_path)? {
             let entry = entry?;
             let path = entry.path();
 
-            // Skip directories, Imgs folder, and hidden files
-            if path.is_dir()
-                || path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|n| n.starts_with('.') || n == "Imgs")
-                    .unwrap_or(true)
-            {
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+
+            // Skip hidden files and folders
+            if file_name.starts_with('.') {
+                continue;
+            }
+
+            let is_dir = path.is_dir();
+            if let Some(rule) = rules.matched(&path, is_dir) {
+                let reason = SkipReason::Excluded(rule);
+                debug!("Skipping {}: {}", path.display(), reason);
+                summary.skipped.push(SkippedFile { path, reason });
                 continue;
             }
 
-            // Skip certain file types
-            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                let ext_lower = ext.to_lowercase();
-
-                // Skip non-ROM files
-                if ext_lower == "xml"
-                    || ext_lower == "miyoocmd"
-                    || ext_lower == "cfg"
-                    || ext_lower == "db"
-                    || ext_lower == "nfo"
-                {
-                    continue;
+            // Recurse into subfolders, except for image and guide folders
+            if is_dir {
+                if depth < self.max_depth && !self.ignored_folders.iter().any(|f| f == file_name) {
+                    self.scan_console_folder(
+                        console_path,
+                        &path,
+                        depth + 1,
+                        console,
+                        rules,
+                        summary,
+                    )?;
                 }
+                continue;
             }
 
-            let name = path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("")
-                .to_string();
+            let console = match console {
+                Some(console) => console,
+                None => match self.detect_console(&path, file_name) {
+                    Some(console) => {
+                        debug!("Detected {} as {}", path.display(), console.name);
+                        console
+                    }
+                    None => {
+                        let reason = SkipReason::UnknownConsole;
+                        debug!("Skipping {}: {}", path.display(), reason);
+                        summary.skipped.push(SkippedFile { path, reason });
+                        continue;
+                    }
+                },
+            };
 
-            let name_no_extension = path
-                .file_stem()
-                .and_then(|n| n.to_str())
-                .unwrap_or("")
-                .to_string();
+            // Only keep files the console accepts
+            if !console.accepts_file(file_name) {
+                let extension = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_lowercase());
+                let reason = SkipReason::UnsupportedExtension(extension);
+                debug!("Skipping {}: {}", path.display(), reason);
+                summary.skipped.push(SkippedFile { path, reason });
+                continue;
+            }
 
-            rom_files.push(RomFile {
-                path: path.clone(),
-                name,
-                name_no_extension,
-                console: console.clone(),
-            });
+            let mut rom = RomFile::new(path.clone(), console.clone());
+            if let Ok(relative_path) = path.strip_prefix(console_path) {
+                rom.relative_path = relative_path.to_path_buf();
+            }
+            summary.roms.push(rom);
         }
 
-        Ok(rom_files)
+        Ok(())
+    }
+
+    /// Identify the console of a file outside any console folder, by exact file name or header
+    fn detect_console(&self, path: &Path, file_name: &str) -> Option<&Console> {
+        if let Some(console) = self.consoles_config.find_console_by_file_name(file_name) {
+            return Some(console);
+        }
+
+        let system = detect::sniff_file(path)
+            .inspect_err(|e| debug!("Failed to read {}: {}", path.display(), e))
+            .ok()
+            .flatten()?;
+        self.consoles_config
+            .find_console_by_screenscraper_id(system.screenscraper_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_filters_by_extension() {
+        let temp_dir = std::env::temp_dir().join("collie_test_scan_extensions");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let gba_dir = temp_dir.join("GBA");
+        std::fs::create_dir_all(gba_dir.join("Imgs")).unwrap();
+        for file in [
+            "Metroid Fusion (USA).gba",
+            "Advance Wars (USA).zip",
+            "Metroid Fusion (USA).srm",
+            "readme.txt",
+            "gamelist.xml",
+            ".hidden.gba",
+        ] {
+            std::fs::write(gba_dir.join(file), "").unwrap();
+        }
+
+        let scanner = RomScanner::new(ConsolesConfig::from_embedded().unwrap());
+        let summary = scanner.scan_directory(&temp_dir).unwrap();
+
+        let mut roms: Vec<_> = summary.roms.iter().map(|r| r.name.as_str()).collect();
+        roms.sort();
+        assert_eq!(roms, ["Advance Wars (USA).zip", "Metroid Fusion (USA).gba"]);
+
+        assert_eq!(summary.skipped.len(), 3);
+        assert!(summary.skipped.iter().any(|s| {
+            s.path.ends_with("readme.txt")
+                && s.reason == SkipReason::UnsupportedExtension(Some("txt".to_string()))
+        }));
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_scan_nested_folders() {
+        let temp_dir = std::env::temp_dir().join("collie_test_scan_nested");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let gba_dir = temp_dir.join("GBA");
+        std::fs::create_dir_all(gba_dir.join("RPG").join("Tactics")).unwrap();
+        std::fs::create_dir_all(gba_dir.join("Imgs")).unwrap();
+        std::fs::write(gba_dir.join("Metroid Fusion (USA).gba"), "").unwrap();
+        std::fs::write(gba_dir.join("RPG").join("Golden Sun (USA).gba"), "").unwrap();
+        std::fs::write(
+            gba_dir
+                .join("RPG")
+                .join("Tactics")
+                .join("Advance Wars (USA).gba"),
+            "",
+        )
+        .unwrap();
+        std::fs::write(gba_dir.join("Imgs").join("Not A Rom.gba"), "").unwrap();
+
+        let scanner = RomScanner::new(ConsolesConfig::from_embedded().unwrap());
+        let summary = scanner.scan_directory(&temp_dir).unwrap();
+        assert_eq!(summary.roms.len(), 3);
+
+        let golden_sun = summary
+            .roms
+            .iter()
+            .find(|r| r.name == "Golden Sun (USA).gba")
+            .unwrap();
+        assert_eq!(
+            golden_sun.relative_path,
+            PathBuf::from("RPG").join("Golden Sun (USA).gba")
+        );
+        assert_eq!(golden_sun.relative_dir(), Path::new("RPG"));
+        assert_eq!(golden_sun.console_dir(), gba_dir);
+
+        // Depth limit stops before the second level
+        let scanner = RomScanner::new(ConsolesConfig::from_embedded().unwrap()).with_max_depth(1);
+        let summary = scanner.scan_directory(&temp_dir).unwrap();
+        assert_eq!(summary.roms.len(), 2);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_scan_detects_loose_roms() {
+        let temp_dir = std::env::temp_dir().join("collie_test_scan_detect");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let misc_dir = temp_dir.join("Misc Games");
+        std::fs::create_dir_all(misc_dir.join("Cave Story")).unwrap();
+        std::fs::write(misc_dir.join("Cave Story").join("Doukutsu.exe"), "").unwrap();
+        std::fs::write(misc_dir.join("Cave Story").join("DirectX.dll"), "").unwrap();
+
+        let mut nes_rom = b"NES\x1A\x02\x01".to_vec();
+        nes_rom.resize(0x4010, 0);
+        std::fs::write(misc_dir.join("mystery.nes"), nes_rom).unwrap();
+
+        // Folders matching no console are left alone unless asked
+        let scanner = RomScanner::new(ConsolesConfig::from_embedded().unwrap());
+        let summary = scanner.scan_directory(&temp_dir).unwrap();
+        assert!(summary.roms.is_empty());
+        assert!(summary.skipped.is_empty());
+
+        let scanner = scanner.with_detect_unknown_folders(true);
+        let summary = scanner.scan_directory(&temp_dir).unwrap();
+
+        let mut roms: Vec<_> = summary
+            .roms
+            .iter()
+            .map(|r| (r.name.as_str(), r.console.name.as_str()))
+            .collect();
+        roms.sort();
+        assert_eq!(
+            roms,
+            [("Doukutsu.exe", "Cave Story"), ("mystery.nes", "NES")]
+        );
+
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.skipped[0].reason, SkipReason::UnknownConsole);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_scan_honors_collieignore() {
+        let temp_dir = std::env::temp_dir().join("collie_test_scan_collieignore");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+
+        let gba_dir = temp_dir.join("GBA");
+        std::fs::create_dir_all(gba_dir.join("_backup")).unwrap();
+        std::fs::create_dir_all(temp_dir.join("BIOS")).unwrap();
+        std::fs::write(gba_dir.join("Golden Sun (USA).gba"), "").unwrap();
+        std::fs::write(gba_dir.join("gba_bios.bin"), "").unwrap();
+        std::fs::write(gba_dir.join("_backup").join("Old (USA).gba"), "").unwrap();
+
+        std::fs::write(temp_dir.join(".collieignore"), "/BIOS/\n_backup/\n").unwrap();
+        std::fs::write(gba_dir.join(".collieignore"), "*bios*\n").unwrap();
+
+        let scanner = RomScanner::new(ConsolesConfig::from_embedded().unwrap());
+        let summary = scanner.scan_directory(&temp_dir).unwrap();
+
+        assert_eq!(summary.roms.len(), 1);
+        assert_eq!(summary.roms[0].name, "Golden Sun (USA).gba");
+
+        let mut excluded: Vec<_> = summary
+            .excluded()
+            .map(|(path, rule)| {
+                (
+                    path.strip_prefix(&temp_dir).unwrap().to_path_buf(),
+                    rule.pattern.as_str(),
+                )
+            })
+            .collect();
+        excluded.sort();
+        assert_eq!(
+            excluded,
+            [
+                (PathBuf::from("BIOS"), "/BIOS/"),
+                (PathBuf::from("GBA/_backup"), "_backup/"),
+                (PathBuf::from("GBA/gba_bios.bin"), "*bios*"),
+            ]
+        );
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
     }
 }
@@ -2,6 +2,7 @@ use itertools::Itertools;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::filename::ParsedName;
 use crate::scraper::{GuidesScraper, ScraperError, ScraperResult};
 use std::ffi::OsStr;
 use std::io::{BufRead, BufReader, Write};
@@ -26,25 +27,31 @@ impl GameFAQsScraper {
         }
     }
 
+    /// Use another gopher server, e.g. a mirror or a local stand-in for tests
+    pub fn with_host(mut self, host: impl Into<String>, port: u16) -> Self {
+        self.host = host.into();
+        self.port = port;
+        self
+    }
+
     /// Normalize game name to match GameFAQs archive structure
     /// GameFAQs uses lowercase with hyphens
     fn normalized_name(path: &Path) -> String {
         let name = path
             .file_stem()
             .and_then(std::ffi::OsStr::to_str)
-            .unwrap_or("")
-            .to_string();
+            .unwrap_or("");
+        Self::normalize(name)
+    }
 
+    /// Normalize a game name without extension, e.g. "Mother 3 (Japan)" -> "mother-3"
+    fn normalize(name: &str) -> String {
         // Remove numbers
         static NUMBERS_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d+[.\)]").unwrap());
-        let name = NUMBERS_RE.replace(&name, "").to_string();
-
-        // Remove trailing parenthesis
-        static PARENTHESIS_RE: LazyLock<Regex> =
-            LazyLock::new(|| Regex::new(r"[\(\[].+[\)\]]$").unwrap());
-        let name = PARENTHESIS_RE.replace(&name, "").to_string();
+        let name = NUMBERS_RE.replace(name, "").to_string();
 
-        let name = name.trim();
+        // Remove region, revision and dump tags
+        let name = ParsedName::parse(&name).title;
         let name = name.to_lowercase();
 
         // Mario & Luigi -> Mario and Luigi
@@ -151,39 +158,19 @@ impl GameFAQsScraper {
             path: parts[1].to_string(),
         })
     }
-}
-
-impl Default for GameFAQsScraper {
-    fn default() -> Self {
-        Self::new()
-    }
-}
 
-#[async_trait::async_trait]
-impl GuidesScraper for GameFAQsScraper {
-    fn name(&self) -> &'static str {
-        "GameFAQs"
-    }
-
-    async fn search_game_guides(
+    /// List the guides in a game's archive folder
+    async fn list_guides(
         &self,
-        path: &Path,
+        normalized_name: &str,
         console: &crate::console::Console,
     ) -> crate::scraper::ScraperResult<Vec<String>> {
-        tracing::debug!(
-            "Searching GameFAQs guides for '{}' on console '{}' ({:?})",
-            path.file_name().and_then(OsStr::to_str).unwrap_or(""),
-            console.name,
-            console.gamefaqs_archive_id
-        );
         // Get the GameFAQs platform identifier from the console
         let platform = console
             .gamefaqs_archive_id
             .as_ref()
             .ok_or(crate::scraper::ScraperError::PlatformNotSupported)?;
 
-        let normalized_name = Self::normalized_name(path);
-
         // Try to list the game directory
         let path = format!("/gamefaqs-archive/{}/{}", platform, normalized_name);
 
@@ -203,6 +190,49 @@ impl GuidesScraper for GameFAQsScraper {
             }
         }
     }
+}
+
+impl Default for GameFAQsScraper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl GuidesScraper for GameFAQsScraper {
+    fn name(&self) -> &'static str {
+        "GameFAQs"
+    }
+
+    async fn search_game_guides(
+        &self,
+        path: &Path,
+        console: &crate::console::Console,
+    ) -> crate::scraper::ScraperResult<Vec<String>> {
+        tracing::debug!(
+            "Searching GameFAQs guides for '{}' on console '{}' ({:?})",
+            path.file_name().and_then(OsStr::to_str).unwrap_or(""),
+            console.name,
+            console.gamefaqs_archive_id
+        );
+        self.list_guides(&Self::normalized_name(path), console)
+            .await
+    }
+
+    async fn search_game_guides_by_name(
+        &self,
+        name: &str,
+        _path: &Path,
+        console: &crate::console::Console,
+    ) -> crate::scraper::ScraperResult<Vec<String>> {
+        tracing::debug!(
+            "Searching GameFAQs guides for '{}' on console '{}' ({:?})",
+            name,
+            console.name,
+            console.gamefaqs_archive_id
+        );
+        self.list_guides(&Self::normalize(name), console).await
+    }
 
     async fn download_guide(
         &self,
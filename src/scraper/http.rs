@@ -0,0 +1,518 @@
+use backoff::ExponentialBackoff;
+use backoff::backoff::Backoff;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use super::media::MediaKind;
+use super::{ScraperError, ScraperResult};
+
+/// Sent with every request so that backends can tell collie's traffic apart
+const USER_AGENT: &str = concat!("collie/", env!("CARGO_PKG_VERSION"));
+
+/// Settings for the HTTP client shared by the metadata scrapers
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub connect_timeout: Duration,
+    /// Longest wait for a response to make progress, which also bounds stalled downloads
+    pub read_timeout: Duration,
+    /// Retries for timeouts, failed connections and 5xx responses
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled with jitter for each following one
+    pub retry_delay: Duration,
+    /// HTTP or SOCKS proxy URL, e.g. `socks5://127.0.0.1:1080`
+    pub proxy: Option<String>,
+    /// Requests per second sent to each host, or 0 for no limit
+    pub requests_per_second: f64,
+    /// Requests that can be sent to a host at once before the rate applies
+    pub burst: u32,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            max_retries: 3,
+            retry_delay: Duration::from_millis(500),
+            proxy: None,
+            requests_per_second: 2.0,
+            burst: 4,
+        }
+    }
+}
+
+/// Token bucket refilled at a steady rate, allowing short bursts
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    /// Tokens left, negative when requests are already waiting for future tokens
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_second: f64, burst: u32, now: Instant) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            capacity,
+            per_second,
+            tokens: capacity,
+            updated: now,
+        }
+    }
+
+    /// Take a token, returning how long to wait before it can be used
+    fn take(&mut self, now: Instant) -> Duration {
+        if self.per_second <= 0.0 {
+            return Duration::ZERO;
+        }
+
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.updated = now;
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.per_second)
+        }
+    }
+}
+
+/// HTTP client with timeouts, retries, an optional proxy and a rate limit for each host
+pub struct HttpClient {
+    client: reqwest::Client,
+    config: HttpConfig,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl HttpClient {
+    pub fn new(config: HttpConfig) -> ScraperResult<Self> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(config.connect_timeout)
+            .read_timeout(config.read_timeout);
+
+        if let Some(proxy) = &config.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| ScraperError::Network(format!("Invalid proxy {}: {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| ScraperError::Network(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Send a GET request once the host's rate limit allows, retrying transient failures.
+    ///
+    /// Responses with an error status are returned as is once retries run out.
+    pub async fn get(&self, url: &str) -> ScraperResult<reqwest::Response> {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_default();
+
+        let mut retry_backoff = ExponentialBackoff {
+            current_interval: self.config.retry_delay,
+            initial_interval: self.config.retry_delay,
+            randomization_factor: 0.5,
+            multiplier: 2.0,
+            max_interval: Duration::from_secs(30),
+            max_elapsed_time: None,
+            ..Default::default()
+        };
+
+        let mut attempt = 0;
+        loop {
+            self.wait_for_token(&host).await;
+
+            let result = self.client.get(url).send().await;
+            let failure = match &result {
+                Ok(response) if response.status().is_server_error() => {
+                    Some(response.status().to_string())
+                }
+                Ok(_) => None,
+                Err(e) if e.is_timeout() || e.is_connect() => Some(e.to_string()),
+                Err(_) => None,
+            };
+
+            let Some(failure) = failure.filter(|_| attempt < self.config.max_retries) else {
+                return result.map_err(|e| ScraperError::Network(e.to_string()));
+            };
+            attempt += 1;
+
+            let delay = retry_backoff
+                .next_backoff()
+                .unwrap_or(retry_backoff.max_interval);
+            warn!(
+                "Request to {} failed ({}), retrying in {}ms ({}/{})",
+                host,
+                failure,
+                delay.as_millis(),
+                attempt,
+                self.config.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Download media to a temporary file next to `destination`, and move it into place only
+    /// once it is checked to be a valid file of its kind
+    pub async fn download(
+        &self,
+        url: &str,
+        kind: MediaKind,
+        destination: &Path,
+    ) -> ScraperResult<()> {
+        let mut response = self.get(url).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ScraperError::InvalidMedia(format!(
+                "{} returned {}",
+                url, status
+            )));
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+        check_content_type(kind, content_type.as_deref()).map_err(ScraperError::InvalidMedia)?;
+
+        let temp_path = temp_path(destination);
+        let result = async {
+            let mut file = tokio::fs::File::create(&temp_path).await?;
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| ScraperError::Network(e.to_string()))?
+            {
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+            drop(file);
+
+            let path = temp_path.clone();
+            tokio::task::spawn_blocking(move || check_file(kind, &path))
+                .await
+                .map_err(|e| ScraperError::InvalidMedia(e.to_string()))?
+                .map_err(|e| ScraperError::InvalidMedia(format!("{} from {}", e, url)))?;
+
+            tokio::fs::rename(&temp_path, destination).await?;
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+        }
+        result
+    }
+
+    /// Wait until the host's token bucket allows another request
+    async fn wait_for_token(&self, host: &str) {
+        let delay = {
+            let now = Instant::now();
+            let mut buckets = self.buckets.lock().unwrap();
+            buckets
+                .entry(host.to_string())
+                .or_insert_with(|| {
+                    TokenBucket::new(self.config.requests_per_second, self.config.burst, now)
+                })
+                .take(now)
+        };
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Get the temporary file a download is written to, in the same folder so that it can be
+/// moved into place atomically
+fn temp_path(destination: &Path) -> PathBuf {
+    let file_name = destination
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    destination.with_file_name(format!(".{}.part", file_name))
+}
+
+/// Reject responses whose content type can't be media of a kind, such as HTML error pages
+fn check_content_type(kind: MediaKind, content_type: Option<&str>) -> Result<(), String> {
+    // Servers that don't say are checked by content alone
+    let Some(content_type) = content_type else {
+        return Ok(());
+    };
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    let expected = match kind {
+        MediaKind::Video => mime.starts_with("video/"),
+        MediaKind::Manual => mime == "application/pdf",
+        _ => mime.starts_with("image/"),
+    };
+    if expected || mime == "application/octet-stream" {
+        Ok(())
+    } else {
+        Err(format!("unexpected content type {}", mime))
+    }
+}
+
+/// Check a downloaded file's magic bytes, and that images decode
+fn check_file(kind: MediaKind, path: &Path) -> Result<(), String> {
+    let mut header = Vec::with_capacity(16);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(16).read_to_end(&mut header))
+        .map_err(|e| e.to_string())?;
+    if header.is_empty() {
+        return Err("empty file".to_string());
+    }
+
+    match kind {
+        MediaKind::Video if header.get(4..8) != Some(b"ftyp".as_slice()) => {
+            Err("not an MP4 video".to_string())
+        }
+        MediaKind::Manual if !header.starts_with(b"%PDF-") => Err("not a PDF".to_string()),
+        MediaKind::Video | MediaKind::Manual => Ok(()),
+        _ => {
+            let format = image::guess_format(&header).map_err(|_| "not an image".to_string())?;
+            image::ImageReader::with_format(
+                std::io::BufReader::new(std::fs::File::open(path).map_err(|e| e.to_string())?),
+                format,
+            )
+            .decode()
+            .map(|_| ())
+            .map_err(|e| format!("broken image: {}", e))
+        }
+    }
+}
+
+impl Default for HttpClient {
+    /// Create a client with the default settings, panicking like `reqwest::Client::new` if TLS
+    /// can't be initialized
+    fn default() -> Self {
+        Self::new(HttpConfig::default()).expect("Failed to create HTTP client")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, http::StatusCode, http::header, routing::get};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use test_case::test_case;
+
+    fn png() -> Vec<u8> {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(2, 3)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        png.into_inner()
+    }
+
+    #[test]
+    fn test_token_bucket_allows_burst_then_rate() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, 2, start);
+
+        assert_eq!(bucket.take(start), Duration::ZERO);
+        assert_eq!(bucket.take(start), Duration::ZERO);
+        // Requests past the burst wait for their own token
+        assert_eq!(bucket.take(start), Duration::from_millis(500));
+        assert_eq!(bucket.take(start), Duration::from_millis(1000));
+
+        // Refilled after a while, up to the burst size
+        let later = start + Duration::from_secs(10);
+        assert_eq!(bucket.take(later), Duration::ZERO);
+        assert_eq!(bucket.take(later), Duration::ZERO);
+        assert_eq!(bucket.take(later), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_token_bucket_without_limit() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(0.0, 1, start);
+        for _ in 0..10 {
+            assert_eq!(bucket.take(start), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_invalid_proxy() {
+        let config = HttpConfig {
+            proxy: Some("not a proxy".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            HttpClient::new(config),
+            Err(ScraperError::Network(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors() {
+        // Fails twice with 503 before answering
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = Router::new().route(
+            "/",
+            get(move || async move {
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => StatusCode::SERVICE_UNAVAILABLE,
+                    _ => StatusCode::OK,
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = HttpConfig {
+            retry_delay: Duration::from_millis(10),
+            requests_per_second: 0.0,
+            ..Default::default()
+        };
+        let client = HttpClient::new(config.clone()).unwrap();
+        let response = client.get(&url).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        // Gives up and returns the error response once retries run out
+        requests.store(0, Ordering::SeqCst);
+        let client = HttpClient::new(HttpConfig {
+            max_retries: 1,
+            ..config
+        })
+        .unwrap();
+        let response = client.get(&url).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test_case(MediaKind::BoxFront, Some("image/png"), true)]
+    #[test_case(MediaKind::Screenshot, Some("image/jpeg; charset=binary"), true)]
+    #[test_case(MediaKind::BoxFront, Some("text/html; charset=UTF-8"), false)]
+    #[test_case(MediaKind::BoxFront, Some("application/json"), false)]
+    #[test_case(MediaKind::BoxFront, Some("application/octet-stream"), true)]
+    #[test_case(MediaKind::BoxFront, None, true)]
+    #[test_case(MediaKind::Video, Some("video/mp4"), true)]
+    #[test_case(MediaKind::Video, Some("image/png"), false)]
+    #[test_case(MediaKind::Manual, Some("application/pdf"), true)]
+    #[test_case(MediaKind::Manual, Some("text/plain"), false)]
+    fn test_check_content_type(kind: MediaKind, content_type: Option<&str>, valid: bool) {
+        assert_eq!(check_content_type(kind, content_type).is_ok(), valid);
+    }
+
+    #[test]
+    fn test_check_file() {
+        let temp_dir = std::env::temp_dir().join("collie_test_check_file");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        let file = |name: &str, contents: &[u8]| {
+            let path = temp_dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+
+        assert!(check_file(MediaKind::BoxFront, &file("box.png", &png())).is_ok());
+        // ScreenScraper answers some failures with a text message
+        let error = file("error.png", "Erreur : Media non trouvé !".as_bytes());
+        assert!(check_file(MediaKind::BoxFront, &error).is_err());
+        assert!(check_file(MediaKind::BoxFront, &file("empty.png", b"")).is_err());
+        // Truncated images have the right magic bytes but don't decode
+        let truncated = file("truncated.png", &png()[..20]);
+        assert!(check_file(MediaKind::BoxFront, &truncated).is_err());
+
+        let video = file("video.mp4", b"\0\0\0\x18ftypmp42\0\0\0\0");
+        assert!(check_file(MediaKind::Video, &video).is_ok());
+        assert!(check_file(MediaKind::Video, &error).is_err());
+        assert!(check_file(MediaKind::Manual, &file("manual.pdf", b"%PDF-1.4\n")).is_ok());
+        assert!(check_file(MediaKind::Manual, &error).is_err());
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_download_only_keeps_valid_media() {
+        let app = Router::new()
+            .route(
+                "/box.png",
+                get(|| async { ([(header::CONTENT_TYPE, "image/png")], png()) }),
+            )
+            .route(
+                "/error.png",
+                get(|| async { ([(header::CONTENT_TYPE, "text/html")], "<h1>Error</h1>") }),
+            )
+            .route(
+                "/broken.png",
+                get(|| async { ([(header::CONTENT_TYPE, "image/png")], "not a png") }),
+            )
+            .route("/missing.png", get(|| async { StatusCode::NOT_FOUND }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let temp_dir = std::env::temp_dir().join("collie_test_download");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        let destination = temp_dir.join("Metroid Fusion (USA).png");
+
+        let client = HttpClient::new(HttpConfig {
+            requests_per_second: 0.0,
+            ..Default::default()
+        })
+        .unwrap();
+
+        for path in ["error.png", "broken.png", "missing.png"] {
+            let result = client
+                .download(
+                    &format!("{base_url}/{path}"),
+                    MediaKind::BoxFront,
+                    &destination,
+                )
+                .await;
+            assert!(
+                matches!(result, Err(ScraperError::InvalidMedia(_))),
+                "{path}"
+            );
+        }
+        // Nothing is left behind, not even the temporary file
+        assert_eq!(std::fs::read_dir(&temp_dir).unwrap().count(), 0);
+
+        client
+            .download(
+                &format!("{base_url}/box.png"),
+                MediaKind::BoxFront,
+                &destination,
+            )
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&destination).unwrap(), png());
+        assert_eq!(std::fs::read_dir(&temp_dir).unwrap().count(), 1);
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+
+/// A kind of media a backend can provide for a game, named after ScreenScraper's media types
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MediaKind {
+    #[default]
+    #[serde(rename = "box-2D")]
+    BoxFront,
+    #[serde(rename = "box-2D-back")]
+    BoxBack,
+    #[serde(rename = "box-2D-side")]
+    BoxSide,
+    #[serde(rename = "box-3D")]
+    Box3D,
+    #[serde(rename = "box-texture")]
+    BoxTexture,
+    /// Screenshot, box and logo composite
+    #[serde(rename = "mixrbv1")]
+    MixV1,
+    #[serde(rename = "mixrbv2")]
+    MixV2,
+    #[serde(rename = "ss")]
+    Screenshot,
+    #[serde(rename = "sstitle")]
+    TitleScreen,
+    /// Transparent game logo
+    #[serde(rename = "wheel")]
+    Logo,
+    /// Arcade cabinet marquee
+    #[serde(rename = "marquee")]
+    Marquee,
+    #[serde(rename = "screenmarquee")]
+    ScreenMarquee,
+    #[serde(rename = "fanart")]
+    Fanart,
+    #[serde(rename = "steamgrid")]
+    SteamGrid,
+    /// Cartridge or disc label
+    #[serde(rename = "support-2D")]
+    Support,
+    #[serde(rename = "support-texture")]
+    SupportTexture,
+    #[serde(rename = "video")]
+    Video,
+    #[serde(rename = "manuel")]
+    Manual,
+}
+
+impl MediaKind {
+    pub const ALL: [MediaKind; 18] = [
+        MediaKind::BoxFront,
+        MediaKind::BoxBack,
+        MediaKind::BoxSide,
+        MediaKind::Box3D,
+        MediaKind::BoxTexture,
+        MediaKind::MixV1,
+        MediaKind::MixV2,
+        MediaKind::Screenshot,
+        MediaKind::TitleScreen,
+        MediaKind::Logo,
+        MediaKind::Marquee,
+        MediaKind::ScreenMarquee,
+        MediaKind::Fanart,
+        MediaKind::SteamGrid,
+        MediaKind::Support,
+        MediaKind::SupportTexture,
+        MediaKind::Video,
+        MediaKind::Manual,
+    ];
+
+    /// Get the ScreenScraper media type, e.g. "sstitle"
+    pub fn screenscraper_type(&self) -> &'static str {
+        match self {
+            MediaKind::BoxFront => "box-2D",
+            MediaKind::BoxBack => "box-2D-back",
+            MediaKind::BoxSide => "box-2D-side",
+            MediaKind::Box3D => "box-3D",
+            MediaKind::BoxTexture => "box-texture",
+            MediaKind::MixV1 => "mixrbv1",
+            MediaKind::MixV2 => "mixrbv2",
+            MediaKind::Screenshot => "ss",
+            MediaKind::TitleScreen => "sstitle",
+            MediaKind::Logo => "wheel",
+            MediaKind::Marquee => "marquee",
+            MediaKind::ScreenMarquee => "screenmarquee",
+            MediaKind::Fanart => "fanart",
+            MediaKind::SteamGrid => "steamgrid",
+            MediaKind::Support => "support-2D",
+            MediaKind::SupportTexture => "support-texture",
+            MediaKind::Video => "video",
+            MediaKind::Manual => "manuel",
+        }
+    }
+
+    /// Get the kind of a ScreenScraper media type
+    pub fn from_screenscraper_type(media_type: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.screenscraper_type() == media_type)
+    }
+
+    /// Get the folder this kind is downloaded into, inside the images folder
+    pub fn folder(&self) -> &'static str {
+        match self {
+            MediaKind::BoxFront => "box",
+            MediaKind::BoxBack => "box-back",
+            MediaKind::BoxSide => "box-side",
+            MediaKind::Box3D => "box-3d",
+            MediaKind::BoxTexture => "box-texture",
+            MediaKind::MixV1 => "mix-v1",
+            MediaKind::MixV2 => "mix-v2",
+            MediaKind::Screenshot => "screenshots",
+            MediaKind::TitleScreen => "titles",
+            MediaKind::Logo => "logos",
+            MediaKind::Marquee => "marquees",
+            MediaKind::ScreenMarquee => "screen-marquees",
+            MediaKind::Fanart => "fanart",
+            MediaKind::SteamGrid => "steamgrid",
+            MediaKind::Support => "labels",
+            MediaKind::SupportTexture => "label-textures",
+            MediaKind::Video => "videos",
+            MediaKind::Manual => "manuals",
+        }
+    }
+
+    /// Get the file extension media of this kind is saved with
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MediaKind::Video => "mp4",
+            MediaKind::Manual => "pdf",
+            _ => "png",
+        }
+    }
+
+    /// Check whether this kind is a still image that can be resized
+    pub fn is_image(&self) -> bool {
+        !matches!(self, MediaKind::Video | MediaKind::Manual)
+    }
+}
+
+/// Deserialize a media kind that must be a still image, such as the kind used as box art
+pub fn deserialize_image_kind<'de, D>(deserializer: D) -> Result<MediaKind, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let kind = MediaKind::deserialize(deserializer)?;
+    if !kind.is_image() {
+        return Err(serde::de::Error::custom(format!(
+            "{} is not an image and can't be used as box art",
+            kind.screenscraper_type()
+        )));
+    }
+    Ok(kind)
+}
+
+/// A media file offered by a backend
+#[derive(Debug, Clone, PartialEq)]
+pub struct Media {
+    pub kind: MediaKind,
+    /// Region code, e.g. "us", if the backend has regional media
+    pub region: Option<String>,
+    pub url: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screenscraper_types_round_trip() {
+        for kind in MediaKind::ALL {
+            assert_eq!(
+                MediaKind::from_screenscraper_type(kind.screenscraper_type()),
+                Some(kind)
+            );
+            // Settings use the same names as ScreenScraper
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                kind.screenscraper_type()
+            );
+        }
+        assert_eq!(MediaKind::from_screenscraper_type("box-2D-top"), None);
+    }
+
+    #[test]
+    fn test_deserialize_image_kind() {
+        #[derive(Deserialize)]
+        struct Config {
+            #[serde(deserialize_with = "deserialize_image_kind")]
+            box_art: MediaKind,
+        }
+
+        let config: Config = serde_json::from_str(r#"{"box_art": "box-3D"}"#).unwrap();
+        assert_eq!(config.box_art, MediaKind::Box3D);
+
+        for kind in ["video", "manuel"] {
+            let json = format!(r#"{{"box_art": "{}"}}"#, kind);
+            assert!(serde_json::from_str::<Config>(&json).is_err());
+        }
+    }
+}
@@ -1,9 +1,16 @@
 pub mod gamefaqs;
+pub mod http;
+pub mod media;
+pub mod preferences;
+pub mod ranking;
 pub mod screenscraper;
 pub mod thegamesdb;
 
 use std::path::Path;
 
+use crate::scanner::RomFile;
+use media::{Media, MediaKind};
+
 /// Metadata for a game scraped from a backend
 #[derive(Debug, Clone)]
 pub struct GameMetadata {
@@ -15,8 +22,62 @@ pub struct GameMetadata {
     pub genre: Option<String>,
     pub players: Option<String>,
     pub rating: Option<f32>,
-    pub image_url: Option<String>,
+    /// Box art, screenshots, videos and other media, most preferred first within each kind
+    pub media: Vec<Media>,
+    pub thumbnail_url: Option<String>,
+    /// How closely the result matched the ROM, from 0 to 1, for backends that rank search results
+    pub confidence: Option<f32>,
+}
+
+impl GameMetadata {
+    /// Get the most preferred media URL of a kind
+    pub fn media_url(&self, kind: MediaKind) -> Option<&str> {
+        self.media
+            .iter()
+            .find(|media| media.kind == kind)
+            .map(|media| media.url.as_str())
+    }
+}
+
+/// A possible match for a ROM, listed so that the user can pick the right game
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameCandidate {
+    /// Backend game ID, as used by `get_game_metadata` and overrides
+    pub id: String,
+    pub title: String,
+    pub platform: Option<String>,
+    pub year: Option<u32>,
     pub thumbnail_url: Option<String>,
+    /// How closely the candidate matches the ROM, from 0 to 1
+    pub confidence: f32,
+}
+
+/// Requests kept in reserve when a daily quota runs low, so that a game is never left half scraped
+pub const QUOTA_RESERVE: u64 = 10;
+
+/// Daily request quota and limits of a backend account
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Quota {
+    /// Number of requests the account may make at once
+    pub max_threads: Option<u32>,
+    pub requests_today: u64,
+    pub max_requests_per_day: u64,
+    /// Download speed limit in KB/s
+    pub max_download_speed: Option<u32>,
+}
+
+impl Quota {
+    /// Get the number of requests left today
+    pub fn remaining(&self) -> u64 {
+        self.max_requests_per_day
+            .saturating_sub(self.requests_today)
+    }
+
+    /// Check whether too few requests are left to scrape another game
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining() < QUOTA_RESERVE
+    }
 }
 
 /// Result of a scraping operation
@@ -37,12 +98,18 @@ pub enum ScraperError {
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
 
+    #[error("Daily quota exceeded")]
+    QuotaExceeded,
+
     #[error("Parse error: {0}")]
     ParseError(String),
 
     #[error("Platform not supported")]
     PlatformNotSupported,
 
+    #[error("Invalid media: {0}")]
+    InvalidMedia(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -52,17 +119,63 @@ pub enum ScraperError {
 pub trait MetadataScraper: Send + Sync {
     fn name(&self) -> &'static str;
 
+    /// Get the account's quota as of the last request, for backends that report one
+    fn quota(&self) -> Option<Quota> {
+        None
+    }
+
     /// Authenticate with the scraping service if required
     async fn authenticate(&mut self, username: &str, password: &str) -> ScraperResult<()>;
 
-    /// Search for a game by name and console
-    async fn search_game(&self, path: &Path, console: &Console) -> ScraperResult<GameMetadata>;
+    /// Search for a scanned ROM by its file and parsed file name
+    async fn search_game(&self, rom: &RomFile) -> ScraperResult<GameMetadata>;
+
+    /// Search for a game using a canonical name, e.g. from a No-Intro DAT, as the search term.
+    ///
+    /// Backends that only search by file fall back to `search_game`.
+    async fn search_game_by_name(&self, _name: &str, rom: &RomFile) -> ScraperResult<GameMetadata> {
+        self.search_game(rom).await
+    }
+
+    /// Search for a game by the product serial of its disc, e.g. `SLUS-00594`.
+    ///
+    /// Backends without serial lookup return `GameNotFound` so that `search_game` is used instead.
+    async fn search_game_by_serial(
+        &self,
+        _serial: &str,
+        _rom: &RomFile,
+    ) -> ScraperResult<GameMetadata> {
+        Err(ScraperError::GameNotFound)
+    }
+
+    /// List the games matching a name, e.g. a file stem or canonical name, best match first.
+    ///
+    /// Backends that can't list search results return no candidates.
+    async fn search_candidates(
+        &self,
+        _name: &str,
+        _console: &Console,
+    ) -> ScraperResult<Vec<GameCandidate>> {
+        Ok(Vec::new())
+    }
 
     /// Get detailed metadata for a specific game
     async fn get_game_metadata(&self, game_id: &str) -> ScraperResult<GameMetadata>;
 
     /// Download an image to a local path
     async fn download_image(&self, url: &str, destination: &Path) -> ScraperResult<()>;
+
+    /// Download media of any kind to a local path.
+    ///
+    /// Backends that only serve images fall back to `download_image`.
+    async fn download_media(
+        &self,
+        url: &str,
+        _kind: MediaKind,
+        destination: &Path,
+    ) -> ScraperResult<()> {
+        self.download_image(url, destination).await
+    }
 }
 
 use crate::console::Console;
@@ -79,6 +192,18 @@ pub trait GuidesScraper: Send + Sync {
         console: &Console,
     ) -> ScraperResult<Vec<String>>;
 
+    /// Search for guides using a canonical name, e.g. an arcade set's full title.
+    ///
+    /// Backends that only search by file fall back to `search_game_guides`.
+    async fn search_game_guides_by_name(
+        &self,
+        _name: &str,
+        path: &Path,
+        console: &Console,
+    ) -> ScraperResult<Vec<String>> {
+        self.search_game_guides(path, console).await
+    }
+
     /// Download a guide to a local path
     async fn download_guide(&self, guide_path: &str, destination: &Path) -> ScraperResult<()>;
 }
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+use crate::filename::ParsedName;
+
+/// ScreenScraper region codes for the region names parsed from file names
+const REGION_CODES: &[(&str, &str)] = &[
+    ("USA", "us"),
+    ("Europe", "eu"),
+    ("Japan", "jp"),
+    ("World", "wor"),
+    ("Asia", "asi"),
+    ("Australia", "au"),
+    ("Brazil", "br"),
+    ("Canada", "ca"),
+    ("China", "cn"),
+    ("France", "fr"),
+    ("Germany", "de"),
+    ("Italy", "it"),
+    ("Korea", "kr"),
+    ("Netherlands", "nl"),
+    ("Russia", "ru"),
+    ("Spain", "sp"),
+    ("Sweden", "se"),
+    ("Taiwan", "tw"),
+    ("UK", "uk"),
+];
+
+/// Region and language order used to pick between regional names, synopses, dates and media
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionPreferences {
+    /// Region codes in order of preference, e.g. `["us", "wor", "eu", "jp"]`
+    #[serde(default = "default_regions")]
+    pub regions: Vec<String>,
+    /// Language codes in order of preference, e.g. `["en", "fr"]`
+    #[serde(default = "default_languages")]
+    pub languages: Vec<String>,
+    /// Try the regions parsed from the ROM's file name before `regions`
+    #[serde(default)]
+    pub prefer_rom_region: bool,
+}
+
+fn default_regions() -> Vec<String> {
+    ["us", "wor", "eu", "jp", "ss"].map(String::from).to_vec()
+}
+
+fn default_languages() -> Vec<String> {
+    vec!["en".to_string()]
+}
+
+impl Default for RegionPreferences {
+    fn default() -> Self {
+        Self {
+            regions: default_regions(),
+            languages: default_languages(),
+            prefer_rom_region: false,
+        }
+    }
+}
+
+impl RegionPreferences {
+    /// Get the region codes to try for a ROM, most preferred first
+    pub fn regions_for(&self, rom: &ParsedName) -> Vec<String> {
+        let rom_regions = rom
+            .regions
+            .iter()
+            .filter(|_| self.prefer_rom_region)
+            .filter_map(|name| region_code(name))
+            .map(String::from);
+
+        let mut regions: Vec<String> = Vec::new();
+        for region in rom_regions.chain(self.regions.iter().cloned()) {
+            if !regions.contains(&region) {
+                regions.push(region);
+            }
+        }
+        regions
+    }
+
+    /// Get the region names (as in `ParsedName::regions`) to try for a ROM, most preferred first
+    pub fn region_names_for(&self, rom: &ParsedName) -> Vec<String> {
+        self.regions_for(rom)
+            .iter()
+            .filter_map(|code| region_name(code))
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Get the ScreenScraper code for a region name, e.g. "USA" → "us"
+pub fn region_code(name: &str) -> Option<&'static str> {
+    REGION_CODES
+        .iter()
+        .find(|(region, _)| *region == name)
+        .map(|(_, code)| *code)
+}
+
+/// Get the region name for a ScreenScraper code, e.g. "eu" → "Europe"
+pub fn region_name(code: &str) -> Option<&'static str> {
+    REGION_CODES
+        .iter()
+        .find(|(_, region_code)| *region_code == code)
+        .map(|(name, _)| *name)
+}
+
+/// Position of a value in a preference list, with unlisted values sorting last
+pub fn rank(preferences: &[impl AsRef<str>], value: Option<&str>) -> usize {
+    value
+        .and_then(|value| preferences.iter().position(|p| p.as_ref() == value))
+        .unwrap_or(preferences.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions_for_rom() {
+        let rom = ParsedName::parse("Golden Sun (Europe) (En,Fr,De)");
+
+        let preferences = RegionPreferences::default();
+        assert_eq!(
+            preferences.regions_for(&rom),
+            ["us", "wor", "eu", "jp", "ss"]
+        );
+
+        let preferences = RegionPreferences {
+            prefer_rom_region: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            preferences.regions_for(&rom),
+            ["eu", "us", "wor", "jp", "ss"]
+        );
+        assert_eq!(
+            preferences.region_names_for(&rom),
+            ["Europe", "USA", "World", "Japan"]
+        );
+    }
+
+    #[test]
+    fn test_rank() {
+        let preferences = default_regions();
+        assert_eq!(rank(&preferences, Some("us")), 0);
+        assert_eq!(rank(&preferences, Some("jp")), 3);
+        assert_eq!(rank(&preferences, Some("fr")), 5);
+        assert_eq!(rank(&preferences, None), 5);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::filename::ParsedName;
+
+/// Matches below this confidence are treated as not found rather than risking wrong art
+pub const MIN_CONFIDENCE: f32 = 0.75;
+
+const TITLE_WEIGHT: f32 = 0.7;
+const PLATFORM_WEIGHT: f32 = 0.15;
+const REGION_WEIGHT: f32 = 0.1;
+const YEAR_WEIGHT: f32 = 0.05;
+
+/// A search result returned by a backend, reduced to the fields used for ranking
+#[derive(Debug, Clone, Default)]
+pub struct Candidate {
+    pub title: String,
+    /// Whether the result is on the ROM's platform, if the backend says
+    pub platform_match: Option<bool>,
+    /// Release regions, using the same names as `ParsedName::regions`
+    pub regions: Vec<String>,
+    pub year: Option<u32>,
+}
+
+/// Score a candidate against the ROM's parsed file name, from 0 (no match) to 1
+pub fn score(query: &ParsedName, candidate: &Candidate) -> f32 {
+    let title = title_similarity(&query.title, &candidate.title);
+
+    let platform = match candidate.platform_match {
+        Some(true) => 1.0,
+        Some(false) => 0.0,
+        None => 0.5,
+    };
+
+    // Earlier regions in the query are preferred, and "World" releases match any region
+    let region = if query.regions.is_empty() || candidate.regions.is_empty() {
+        0.5
+    } else {
+        query
+            .regions
+            .iter()
+            .position(|region| {
+                region == "World"
+                    || candidate
+                        .regions
+                        .iter()
+                        .any(|other| other == region || other == "World")
+            })
+            .map(|position| 1.0 - position as f32 / query.regions.len() as f32)
+            .unwrap_or(0.0)
+    };
+
+    let year = match (release_year(query), candidate.year) {
+        (Some(expected), Some(year)) if expected == year => 1.0,
+        (Some(_), Some(_)) => 0.0,
+        _ => 0.5,
+    };
+
+    title * TITLE_WEIGHT + platform * PLATFORM_WEIGHT + region * REGION_WEIGHT + year * YEAR_WEIGHT
+}
+
+/// Pick the highest scoring candidate, or `None` if none reaches `MIN_CONFIDENCE`
+pub fn best_match<T>(
+    query: &ParsedName,
+    candidates: impl IntoIterator<Item = (T, Candidate)>,
+) -> Option<(T, f32)> {
+    rank(query, candidates)
+        .into_iter()
+        .next()
+        .filter(|(_, confidence)| *confidence >= MIN_CONFIDENCE)
+}
+
+/// Score every candidate and sort them from best to worst, keeping those below `MIN_CONFIDENCE`
+pub fn rank<T>(
+    query: &ParsedName,
+    candidates: impl IntoIterator<Item = (T, Candidate)>,
+) -> Vec<(T, f32)> {
+    let mut ranked: Vec<(T, f32)> = candidates
+        .into_iter()
+        .map(|(item, candidate)| {
+            let confidence = score(query, &candidate);
+            (item, confidence)
+        })
+        .collect();
+    ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    ranked
+}
+
+/// Get the year from a release date such as "1986-08-06"
+pub fn parse_year(date: &str) -> Option<u32> {
+    date.get(..4)?.parse().ok()
+}
+
+/// Get the release year from a TOSEC style tag, e.g. "(1989)"
+fn release_year(query: &ParsedName) -> Option<u32> {
+    query.tags.iter().find_map(|tag| {
+        let year: u32 = tag.get(..4)?.parse().ok()?;
+        (tag.len() == 4 && (1970..2100).contains(&year)).then_some(year)
+    })
+}
+
+/// Lowercase a title and drop punctuation and leading articles, so that
+/// "The Legend of Zelda - Minish Cap" and "Legend of Zelda: Minish Cap" compare equal
+fn normalize_title(title: &str) -> String {
+    let title = title.to_lowercase().replace('&', " and ");
+    let words: Vec<&str> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    match words.split_first() {
+        Some((&"the", rest)) if !rest.is_empty() => rest.join(" "),
+        _ => words.join(" "),
+    }
+}
+
+/// Similarity of two titles from 0 to 1, based on the edit distance of their normalized forms
+fn title_similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = normalize_title(a).chars().collect();
+    let b: Vec<char> = normalize_title(b).chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein(&a, &b) as f32 / longest as f32
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use test_case::test_case;
+
+    fn candidate(title: &str) -> Candidate {
+        Candidate {
+            title: title.to_string(),
+            platform_match: Some(true),
+            ..Default::default()
+        }
+    }
+
+    #[test_case(
+        "Legend of Zelda, The - Minish Cap",
+        "The Legend of Zelda: The Minish Cap",
+        0.9
+    )]
+    #[test_case(
+        "Mario & Luigi - Superstar Saga",
+        "Mario and Luigi: Superstar Saga",
+        1.0
+    )]
+    #[test_case("Metroid", "Metroid Prime", 0.5)]
+    fn test_title_similarity(stem: &str, title: &str, expected: f32) {
+        let similarity = title_similarity(&ParsedName::parse(stem).title, title);
+        assert!(
+            (similarity - expected).abs() < 0.1,
+            "{} vs {}: {}",
+            stem,
+            title,
+            similarity
+        );
+    }
+
+    #[test]
+    fn test_best_match_prefers_exact_title() {
+        let query = ParsedName::parse("Metroid (USA)");
+        let (title, confidence) = best_match(
+            &query,
+            ["Metroid Prime", "Metroid", "Metroid II"].map(|title| (title, candidate(title))),
+        )
+        .unwrap();
+        assert_eq!(title, "Metroid");
+        assert!(confidence > 0.9);
+    }
+
+    #[test]
+    fn test_best_match_rejects_low_confidence() {
+        let query = ParsedName::parse("Metroid (USA)");
+        let results = [("Metroid Prime", candidate("Metroid Prime"))];
+        assert!(best_match(&query, results).is_none());
+    }
+
+    #[test]
+    fn test_rank_keeps_every_candidate() {
+        let query = ParsedName::parse("Metroid (USA)");
+        let ranked = rank(
+            &query,
+            ["Metroid Prime", "Metroid", "Super Metroid"].map(|title| (title, candidate(title))),
+        );
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[0].0, "Metroid");
+        assert!(ranked.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+
+    #[test]
+    fn test_region_and_year_break_ties() {
+        let query = ParsedName::parse("Chase H.Q. (1989)(Ocean)(Europe)");
+        let european = Candidate {
+            regions: vec!["Europe".to_string()],
+            year: Some(1989),
+            ..candidate("Chase H.Q.")
+        };
+        let japanese = Candidate {
+            regions: vec!["Japan".to_string()],
+            year: Some(1988),
+            ..candidate("Chase H.Q.")
+        };
+        assert!(score(&query, &european) > score(&query, &japanese));
+        assert_eq!(
+            best_match(&query, [("jp", japanese), ("eu", european)])
+                .unwrap()
+                .0,
+            "eu"
+        );
+    }
+}
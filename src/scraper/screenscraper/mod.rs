@@ -1,15 +1,19 @@
+use crate::archive;
 use crate::console::Console;
-
-use super::{GameMetadata, MetadataScraper, ScraperError, ScraperResult};
+use crate::filename::ParsedName;
+use crate::hashing::{self, Digests, HashIndex};
+use crate::scanner::RomFile;
+
+use super::http::HttpClient;
+use super::media::{self, Media, MediaKind};
+use super::preferences::{self, RegionPreferences};
+use super::ranking::{self, Candidate};
+use super::{GameCandidate, GameMetadata, MetadataScraper, Quota, ScraperError, ScraperResult};
 use async_trait::async_trait;
-use crc32fast::Hasher;
 use serde::{Deserialize, Serialize};
-use std::{
-    fs::{File, Metadata},
-    io::Read,
-    path::Path,
-};
-use tracing::debug;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
 
 const SCREENSCRAPER_API_URL: &str = "https://api.screenscraper.fr/api2";
 const DEV_ID: &str = "Silenced8261";
@@ -21,19 +25,42 @@ const SOFTWARE_NAME: &str = "collie";
 pub struct ScreenScraperConfig {
     pub username: Option<String>,
     pub password: Option<String>,
-    #[serde(default = "default_box_art_type")]
-    pub box_art_type: String,
+    /// Media kind used as the game's main image, which must be a still image
+    #[serde(default, deserialize_with = "media::deserialize_image_kind")]
+    pub box_art_type: MediaKind,
+}
+
+/// ROM size, checksums and serial sent with a jeuInfos lookup
+#[derive(Debug, Default)]
+struct RomChecksums {
+    serial: Option<String>,
+    size: Option<u64>,
+    crc: Option<String>,
+    md5: Option<String>,
+    sha1: Option<String>,
 }
 
-fn default_box_art_type() -> String {
-    "box-2D".to_string()
+impl From<Digests> for RomChecksums {
+    fn from(digests: Digests) -> Self {
+        Self {
+            size: Some(digests.size),
+            crc: Some(digests.crc32),
+            md5: Some(digests.md5),
+            sha1: Some(digests.sha1),
+            ..Default::default()
+        }
+    }
 }
 
 pub struct ScreenScraper {
+    base_url: String,
     username: Option<String>,
     password: Option<String>,
-    box_art_type: String,
-    client: reqwest::Client,
+    hash_index: Option<Arc<HashIndex>>,
+    preferences: RegionPreferences,
+    /// Quota reported with the last response
+    quota: Mutex<Option<Quota>>,
+    http: Arc<HttpClient>,
 }
 
 impl Default for ScreenScraper {
@@ -45,21 +72,43 @@ impl Default for ScreenScraper {
 impl ScreenScraper {
     pub fn new() -> Self {
         Self {
+            base_url: SCREENSCRAPER_API_URL.to_string(),
             username: None,
             password: None,
-            box_art_type: default_box_art_type(),
-            client: reqwest::Client::new(),
+            hash_index: None,
+            preferences: RegionPreferences::default(),
+            quota: Mutex::new(None),
+            http: Arc::new(HttpClient::default()),
         }
     }
 
-    pub fn with_box_art_type(mut self, box_art_type: String) -> Self {
-        self.box_art_type = box_art_type;
+    /// Use another API server, e.g. a mirror or a local stand-in for tests
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Share an HTTP client, and its rate limits, with other scrapers
+    pub fn with_http_client(mut self, http: Arc<HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Reuse ROM checksums stored in a hash index instead of rehashing on every run
+    pub fn with_hash_index(mut self, hash_index: Arc<HashIndex>) -> Self {
+        self.hash_index = Some(hash_index);
+        self
+    }
+
+    /// Set the region and language order for names, synopses, dates and media
+    pub fn with_preferences(mut self, preferences: RegionPreferences) -> Self {
+        self.preferences = preferences;
         self
     }
 
     /// Build API URL with authentication parameters
     fn build_api_url(&self, endpoint: &str, params: &[(&str, &str)]) -> String {
-        let mut url = format!("{}/{}", SCREENSCRAPER_API_URL, endpoint);
+        let mut url = format!("{}/{}", self.base_url, endpoint);
         url.push_str(&format!(
             "?devid={}&devpassword={}&softname={}",
             DEV_ID, DEV_PASSWORD, SOFTWARE_NAME
@@ -78,17 +127,16 @@ impl ScreenScraper {
         url
     }
 
-    /// Search for a game by name, console, and optional CRC
+    /// Search for a game by name, console, and optional checksums
     async fn search_game_internal(
         &self,
         rom_name: &str,
         console_id: &str,
-        rom_size: Option<u64>,
-        crc: Option<String>,
+        checksums: &RomChecksums,
     ) -> ScraperResult<serde_json::Value> {
         debug!(
-            "Searching ScreenScraper for rom_name: {}, console_id: {}, rom_size: {:?}, crc: {:?}",
-            rom_name, console_id, rom_size, crc
+            "Searching ScreenScraper for rom_name: {}, console_id: {}, checksums: {:?}",
+            rom_name, console_id, checksums
         );
 
         let rom_name_encoded = urlencoding::encode(rom_name);
@@ -98,23 +146,40 @@ impl ScreenScraper {
             ("romtype", "rom"),
         ];
 
-        let size_string = rom_size.map(|s| s.to_string());
+        let size_string = checksums.size.map(|s| s.to_string());
         if let Some(ref size_str) = size_string {
             params.push(("romtaille", size_str.as_str()));
         }
 
-        if let Some(crc) = crc.as_ref() {
+        if let Some(serial) = checksums.serial.as_ref() {
+            params.push(("serialnum", serial));
+        }
+        if let Some(crc) = checksums.crc.as_ref() {
             params.push(("crc", crc));
         }
+        if let Some(md5) = checksums.md5.as_ref() {
+            params.push(("md5", md5));
+        }
+        if let Some(sha1) = checksums.sha1.as_ref() {
+            params.push(("sha1", sha1));
+        }
 
-        let url = self.build_api_url("jeuInfos.php", &params);
+        self.request("jeuInfos.php", &params).await
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| ScraperError::Network(e.to_string()))?;
+    /// Request an API endpoint and parse its JSON response
+    async fn request(
+        &self,
+        endpoint: &str,
+        params: &[(&str, &str)],
+    ) -> ScraperResult<serde_json::Value> {
+        if self.quota().is_some_and(|quota| quota.remaining() == 0) {
+            return Err(ScraperError::QuotaExceeded);
+        }
+
+        let url = self.build_api_url(endpoint, params);
+
+        let response = self.http.get(&url).await?;
 
         let status = response.status();
 
@@ -129,44 +194,97 @@ impl ScreenScraper {
             200 => {
                 let json: serde_json::Value = serde_json::from_str(&text)
                     .map_err(|e| ScraperError::ParseError(e.to_string()))?;
+                if let Some(quota) = Self::parse_quota(&json) {
+                    *self.quota.lock().unwrap() = Some(quota);
+                }
                 Ok(json)
             }
             403 => Err(ScraperError::AuthenticationFailed),
             404 => Err(ScraperError::GameNotFound),
-            429..=431 => Err(ScraperError::RateLimitExceeded),
+            430 => Err(ScraperError::QuotaExceeded),
+            429 | 431 => Err(ScraperError::RateLimitExceeded),
             _ => Err(ScraperError::Network(text)),
         }
     }
 
-    /// Extract game metadata from API response
-    fn parse_game_metadata(&self, json: &serde_json::Value) -> ScraperResult<GameMetadata> {
+    /// Extract the account quota from the `ssuser` part of an API response
+    fn parse_quota(json: &serde_json::Value) -> Option<Quota> {
+        let ssuser = json.get("response")?.get("ssuser")?;
+        let number = |key: &str| {
+            let value = ssuser.get(key)?;
+            value
+                .as_u64()
+                .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+        };
+
+        Some(Quota {
+            max_threads: number("maxthreads").and_then(|n| n.try_into().ok()),
+            requests_today: number("requeststoday")?,
+            max_requests_per_day: number("maxrequestsperday")?,
+            max_download_speed: number("maxdownloadspeed").and_then(|n| n.try_into().ok()),
+        })
+    }
+
+    /// Check the user's credentials, returning their quota
+    pub async fn check_credentials(&self) -> ScraperResult<Quota> {
+        if self.username.is_none() || self.password.is_none() {
+            return Err(ScraperError::AuthenticationFailed);
+        }
+
+        let json = self.request("ssuserInfos.php", &[]).await?;
+        Self::parse_quota(&json).ok_or(ScraperError::AuthenticationFailed)
+    }
+
+    /// Extract game metadata and media from an API response
+    fn metadata_with_media(
+        &self,
+        json: &serde_json::Value,
+        rom: &ParsedName,
+    ) -> ScraperResult<GameMetadata> {
+        let regions = self.preferences.regions_for(rom);
+        let mut metadata = self.parse_game_metadata(json, &regions)?;
+        metadata.media = Self::get_media(json, &regions);
+
+        Ok(metadata)
+    }
+
+    /// Pick the text of a regional or translated value, e.g. `noms` or `synopsis`, by
+    /// its position in a preference list
+    fn localized_text<'a>(
+        values: Option<&'a serde_json::Value>,
+        key: &str,
+        order: &[impl AsRef<str>],
+    ) -> Option<&'a str> {
+        values?
+            .as_array()?
+            .iter()
+            .min_by_key(|value| preferences::rank(order, value.get(key).and_then(|k| k.as_str())))
+            .and_then(|value| value.get("text"))
+            .and_then(|t| t.as_str())
+    }
+
+    /// Extract game metadata from API response, preferring names and dates from the given regions
+    fn parse_game_metadata(
+        &self,
+        json: &serde_json::Value,
+        regions: &[String],
+    ) -> ScraperResult<GameMetadata> {
         let jeu = json
             .get("response")
             .and_then(|r| r.get("jeu"))
             .or_else(|| json.get("jeu"))
             .ok_or_else(|| ScraperError::ParseError("Missing 'jeu' field".to_string()))?;
+        let languages = &self.preferences.languages;
 
-        let name = jeu
-            .get("noms")
-            .and_then(|n| n.get(0))
-            .and_then(|n| n.get("text"))
-            .and_then(|t| t.as_str())
+        let name = Self::localized_text(jeu.get("noms"), "region", regions)
             .unwrap_or("Unknown")
             .to_string();
 
-        let description = jeu
-            .get("synopsis")
-            .and_then(|s| s.get(0))
-            .and_then(|s| s.get("text"))
-            .and_then(|t| t.as_str())
-            .map(|s| s.to_string());
+        let description =
+            Self::localized_text(jeu.get("synopsis"), "langue", languages).map(|s| s.to_string());
 
-        let release_date = jeu
-            .get("dates")
-            .and_then(|d| d.get(0))
-            .and_then(|d| d.get("text"))
-            .and_then(|t| t.as_str())
-            .map(|s| s.to_string());
+        let release_date =
+            Self::localized_text(jeu.get("dates"), "region", regions).map(|s| s.to_string());
 
         let developer = jeu
             .get("developpeur")
@@ -183,10 +301,7 @@ impl ScreenScraper {
         let genre = jeu
             .get("genres")
             .and_then(|g| g.get(0))
-            .and_then(|g| g.get("noms"))
-            .and_then(|n| n.get(0))
-            .and_then(|n| n.get("text"))
-            .and_then(|t| t.as_str())
+            .and_then(|g| Self::localized_text(g.get("noms"), "langue", languages))
             .map(|s| s.to_string());
 
         let players = jeu
@@ -211,25 +326,49 @@ impl ScreenScraper {
             genre,
             players,
             rating,
-            image_url: None,
+            media: Vec::new(),
             thumbnail_url: None,
+            confidence: None,
         })
     }
 
+    /// Get the `medias` of an API response, or of a game from a search result
+    fn medias(json: &serde_json::Value) -> Option<&Vec<serde_json::Value>> {
+        json.get("response")
+            .and_then(|r| r.get("jeu"))
+            .and_then(|j| j.get("medias"))
+            .or_else(|| json.get("jeu").and_then(|j| j.get("medias")))
+            .or_else(|| json.get("medias"))?
+            .as_array()
+    }
+
+    /// Get every media of a known kind, most preferred region first
+    fn get_media(json: &serde_json::Value, region_preferences: &[impl AsRef<str>]) -> Vec<Media> {
+        let mut media: Vec<Media> = Self::medias(json)
+            .into_iter()
+            .flatten()
+            .filter_map(|m| {
+                let kind = MediaKind::from_screenscraper_type(m.get("type")?.as_str()?)?;
+                Some(Media {
+                    kind,
+                    region: m.get("region").and_then(|r| r.as_str()).map(String::from),
+                    url: m.get("url")?.as_str()?.to_string(),
+                })
+            })
+            .collect();
+
+        media.sort_by_key(|m| preferences::rank(region_preferences, m.region.as_deref()));
+        media
+    }
+
     /// Get media URL for a specific type and region preferences
     fn get_media_url(
         &self,
         json: &serde_json::Value,
         media_type: &str,
-        region_preferences: &[&str],
+        region_preferences: &[impl AsRef<str>],
     ) -> Option<String> {
-        let medias = json
-            .get("response")
-            .and_then(|r| r.get("jeu"))
-            .and_then(|j| j.get("medias"))
-            .or_else(|| json.get("jeu").and_then(|j| j.get("medias")))?;
-
-        let medias_array = medias.as_array()?;
+        let medias_array = Self::medias(json)?;
 
         // Filter by media type
         let mut matching_medias: Vec<_> = medias_array
@@ -244,15 +383,7 @@ impl ScreenScraper {
 
         // Sort by region preference
         matching_medias.sort_by_key(|m| {
-            let region = m
-                .get("region")
-                .and_then(|r| r.as_str())
-                .unwrap_or("unknown");
-
-            region_preferences
-                .iter()
-                .position(|&r| r == region)
-                .unwrap_or(999)
+            preferences::rank(region_preferences, m.get("region").and_then(|r| r.as_str()))
         });
 
         matching_medias
@@ -261,21 +392,140 @@ impl ScreenScraper {
             .and_then(|u| u.as_str())
             .map(|s| s.to_string())
     }
-}
 
-#[async_trait]
-impl MetadataScraper for ScreenScraper {
-    fn name(&self) -> &'static str {
-        "ScreenScraper"
+    /// Reduce a `jeuRecherche` result to the fields used for ranking
+    fn ranking_candidate(
+        jeu: &serde_json::Value,
+        regions: &[String],
+        console_id: Option<&str>,
+    ) -> Option<Candidate> {
+        jeu.get("id")?;
+        let title = Self::localized_text(jeu.get("noms"), "region", regions)?.to_string();
+        let system_id = jeu.get("systeme").and_then(|s| s.get("id")).map(id_string);
+
+        Some(Candidate {
+            title,
+            platform_match: console_id.zip(system_id).map(|(a, b)| a == b),
+            regions: jeu
+                .get("noms")
+                .and_then(|noms| noms.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|nom| nom.get("region").and_then(|r| r.as_str()))
+                .filter_map(preferences::region_name)
+                .map(String::from)
+                .collect(),
+            year: Self::localized_text(jeu.get("dates"), "region", regions)
+                .and_then(ranking::parse_year),
+        })
     }
 
-    async fn authenticate(&mut self, username: &str, password: &str) -> ScraperResult<()> {
-        self.username = Some(username.to_string());
-        self.password = Some(password.to_string());
-        Ok(())
+    /// Reduce a `jeuRecherche` result to a candidate, ranked against the searched name
+    fn candidate(
+        &self,
+        jeu: &serde_json::Value,
+        query: &ParsedName,
+        console_id: Option<&str>,
+    ) -> Option<GameCandidate> {
+        let regions = self.preferences.regions_for(query);
+        let candidate = Self::ranking_candidate(jeu, &regions, console_id)?;
+
+        Some(GameCandidate {
+            id: id_string(jeu.get("id")?),
+            title: candidate.title.clone(),
+            platform: jeu
+                .get("systeme")
+                .and_then(|s| s.get("text"))
+                .and_then(|t| t.as_str())
+                .map(String::from),
+            year: candidate.year,
+            thumbnail_url: self.get_media_url(
+                jeu,
+                MediaKind::BoxFront.screenscraper_type(),
+                &regions,
+            ),
+            confidence: ranking::score(&self.ranking_query(query), &candidate),
+        })
     }
 
-    async fn search_game(&self, path: &Path, console: &Console) -> ScraperResult<GameMetadata> {
+    /// Search games by title with `jeuRecherche`, returning full game entries
+    async fn search_by_title(
+        &self,
+        title: &str,
+        console_id: Option<&str>,
+    ) -> ScraperResult<Vec<serde_json::Value>> {
+        let title = urlencoding::encode(title);
+        let mut params = vec![("recherche", title.as_ref())];
+        if let Some(console_id) = console_id {
+            params.push(("systemeid", console_id));
+        }
+
+        let mut json = match self.request("jeuRecherche.php", &params).await {
+            Ok(json) => json,
+            Err(ScraperError::GameNotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        match json.get_mut("response").and_then(|r| r.get_mut("jeux")) {
+            Some(serde_json::Value::Array(jeux)) => Ok(std::mem::take(jeux)),
+            _ => Err(ScraperError::ParseError("Missing 'jeux' field".to_string())),
+        }
+    }
+
+    /// Pick the best ranked search result for a ROM, or `None` if none is confident enough
+    fn best_search_result(
+        &self,
+        jeux: Vec<serde_json::Value>,
+        rom: &ParsedName,
+        console_id: &str,
+    ) -> Option<(serde_json::Value, f32)> {
+        let regions = self.preferences.regions_for(rom);
+        ranking::best_match(
+            &self.ranking_query(rom),
+            jeux.into_iter().filter_map(|jeu| {
+                let candidate = Self::ranking_candidate(&jeu, &regions, Some(console_id))?;
+                Some((jeu, candidate))
+            }),
+        )
+    }
+
+    /// Search by title when no checksum lookup matched, e.g. for hacks, translations and
+    /// renamed files
+    async fn search_rom_by_title(
+        &self,
+        rom: &ParsedName,
+        console_id: &str,
+    ) -> ScraperResult<GameMetadata> {
+        debug!("Falling back to a title search for {}", rom.title);
+
+        let jeux = self.search_by_title(&rom.title, Some(console_id)).await?;
+        let Some((jeu, confidence)) = self.best_search_result(jeux, rom, console_id) else {
+            return Err(ScraperError::GameNotFound);
+        };
+
+        let mut metadata = self.metadata_with_media(&serde_json::json!({ "jeu": jeu }), rom)?;
+        metadata.confidence = Some(confidence);
+        Ok(metadata)
+    }
+
+    /// Add the preferred region names after the ROM's own
+    fn ranking_query(&self, query: &ParsedName) -> ParsedName {
+        let mut query = query.clone();
+        for region in self.preferences.region_names_for(&query) {
+            if !query.regions.contains(&region) {
+                query.regions.push(region);
+            }
+        }
+        query
+    }
+
+    /// Search for a ROM by its checksums, optionally sending a canonical name instead of its
+    /// file name
+    async fn search_rom(
+        &self,
+        rom_file: &RomFile,
+        canonical_name: Option<&str>,
+    ) -> ScraperResult<GameMetadata> {
+        let (path, console) = (rom_file.path.as_path(), &rom_file.console);
         let Some(console_id) = console.screenscraper_id.as_ref() else {
             return Err(ScraperError::PlatformNotSupported);
         };
@@ -283,70 +533,177 @@ impl MetadataScraper for ScreenScraper {
         let Some(file_name) = path.file_name() else {
             return Err(ScraperError::GameNotFound);
         };
-        let file_name = file_name.to_string_lossy();
+        let mut file_name = file_name.to_string_lossy().to_string();
 
-        let mut size = None;
-        let mut crc = None;
+        // Checksums to look up, tried in order until one matches
+        let mut lookups = Vec::new();
 
-        if let Ok(mut file) = File::open(path) {
-            size = file.metadata().as_ref().map(Metadata::len).ok();
+        // For archives, identify the ROM inside rather than the archive itself
+        let archive_entry = if archive::holds_single_rom(path, console) {
+            archive::find_rom_entry(path, console).unwrap_or_else(|e| {
+                warn!("Failed to read archive {}: {}", path.display(), e);
+                None
+            })
+        } else {
+            None
+        };
 
-            let mut hasher = Hasher::new();
-            let mut buffer = [0u8; 8192];
-            while let Ok(bytes_read) = file.read(&mut buffer) {
-                if bytes_read == 0 {
-                    break;
+        if let Some(entry) = archive_entry {
+            file_name = entry.file_name().to_string();
+            lookups.push(RomChecksums {
+                size: Some(entry.size),
+                crc: Some(format!("{:08x}", entry.crc)),
+                ..Default::default()
+            });
+        } else {
+            let hashes = match &self.hash_index {
+                Some(index) => index.hashes(path, console).await,
+                None => hashing::hash_file_blocking(path.to_path_buf(), console.clone()).await,
+            };
+            match hashes {
+                Ok(hashes) => {
+                    // Databases hash most headered ROMs without their header
+                    if let Some(headerless) = hashes.headerless {
+                        lookups.push(headerless.into());
+                    }
+                    lookups.push(hashes.raw.into());
                 }
-                hasher.update(&buffer[..bytes_read]);
+                Err(e) => warn!("Failed to hash {}: {}", path.display(), e),
+            }
+        }
+        let rom = match canonical_name {
+            Some(name) => ParsedName::parse(name),
+            None => rom_file.parsed_name.clone(),
+        };
+        if let Some(name) = canonical_name {
+            file_name = match Path::new(&file_name).extension() {
+                Some(extension) => format!("{}.{}", name, extension.to_string_lossy()),
+                None => name.to_string(),
+            };
+        }
+        if lookups.is_empty() {
+            lookups.push(RomChecksums::default());
+        }
+
+        let console_id = console_id.to_string();
+        let mut result = Err(ScraperError::GameNotFound);
+        for checksums in &lookups {
+            result = self
+                .search_game_internal(&file_name, &console_id, checksums)
+                .await;
+            if !matches!(result, Err(ScraperError::GameNotFound)) {
+                break;
             }
-            crc = Some(format!("{:x}", hasher.finalize()));
         }
+        match result {
+            Err(ScraperError::GameNotFound) => self.search_rom_by_title(&rom, &console_id).await,
+            result => self.metadata_with_media(&result?, &rom),
+        }
+    }
+}
+
+/// Get an ID that the API returns either as a string or as a number
+fn id_string(id: &serde_json::Value) -> String {
+    match id {
+        serde_json::Value::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
 
+#[async_trait]
+impl MetadataScraper for ScreenScraper {
+    fn name(&self) -> &'static str {
+        "ScreenScraper"
+    }
+
+    fn quota(&self) -> Option<Quota> {
+        *self.quota.lock().unwrap()
+    }
+
+    async fn authenticate(&mut self, username: &str, password: &str) -> ScraperResult<()> {
+        self.username = Some(username.to_string());
+        self.password = Some(password.to_string());
+        Ok(())
+    }
+
+    async fn search_game(&self, rom: &RomFile) -> ScraperResult<GameMetadata> {
+        self.search_rom(rom, None).await
+    }
+
+    async fn search_game_by_name(&self, name: &str, rom: &RomFile) -> ScraperResult<GameMetadata> {
+        self.search_rom(rom, Some(name)).await
+    }
+
+    async fn search_game_by_serial(
+        &self,
+        serial: &str,
+        rom: &RomFile,
+    ) -> ScraperResult<GameMetadata> {
+        let Some(console_id) = rom.console.screenscraper_id.as_ref() else {
+            return Err(ScraperError::PlatformNotSupported);
+        };
+
+        let checksums = RomChecksums {
+            serial: Some(serial.to_string()),
+            ..Default::default()
+        };
         let result = self
-            .search_game_internal(&file_name, &console_id.to_string(), size, crc)
+            .search_game_internal(&rom.name, &console_id.to_string(), &checksums)
             .await?;
 
-        let mut metadata = self.parse_game_metadata(&result)?;
+        self.metadata_with_media(&result, &rom.parsed_name)
+    }
 
-        // Extract image URL with region preferences
-        let region_preferences = vec!["us", "wor", "eu", "jp", "ss"];
-        let image_url = self.get_media_url(&result, &self.box_art_type, &region_preferences);
-        metadata.image_url = image_url;
+    async fn search_candidates(
+        &self,
+        name: &str,
+        console: &Console,
+    ) -> ScraperResult<Vec<GameCandidate>> {
+        let query = ParsedName::parse(name);
+        let console_id = console.screenscraper_id.map(|id| id.to_string());
+        let jeux = self
+            .search_by_title(&query.title, console_id.as_deref())
+            .await?;
 
-        Ok(metadata)
+        // An empty search returns a single empty game
+        let mut candidates: Vec<GameCandidate> = jeux
+            .iter()
+            .filter_map(|jeu| self.candidate(jeu, &query, console_id.as_deref()))
+            .collect();
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        Ok(candidates)
     }
 
-    async fn get_game_metadata(&self, _game_id: &str) -> ScraperResult<GameMetadata> {
-        // For ScreenScraper, we get metadata from search
-        // This could be extended if we need to fetch by game ID
-        Err(ScraperError::ParseError(
-            "Not implemented for ScreenScraper".to_string(),
-        ))
+    async fn get_game_metadata(&self, game_id: &str) -> ScraperResult<GameMetadata> {
+        let game_id = urlencoding::encode(game_id);
+        let result = self
+            .request("jeuInfos.php", &[("gameid", game_id.as_ref())])
+            .await?;
+
+        // There is no ROM to prefer regions from, so only the configured order applies
+        self.metadata_with_media(&result, &ParsedName::default())
     }
 
     async fn download_image(&self, url: &str, destination: &Path) -> ScraperResult<()> {
-        // Add max dimensions to URL
-        let url_with_params = if url.contains('?') {
+        // Box art is shown as a thumbnail, so ask for it at that size
+        let url = if url.contains('?') {
             format!("{}&maxwidth=250&maxheight=360", url)
         } else {
             format!("{}?maxwidth=250&maxheight=360", url)
         };
 
-        let response = self
-            .client
-            .get(&url_with_params)
-            .send()
-            .await
-            .map_err(|e| ScraperError::Network(e.to_string()))?;
-
-        let bytes = response
-            .bytes()
+        self.http
+            .download(&url, MediaKind::BoxFront, destination)
             .await
-            .map_err(|e| ScraperError::Network(e.to_string()))?;
-
-        tokio::fs::write(destination, bytes).await?;
+    }
 
-        Ok(())
+    async fn download_media(
+        &self,
+        url: &str,
+        kind: MediaKind,
+        destination: &Path,
+    ) -> ScraperResult<()> {
+        self.http.download(url, kind, destination).await
     }
 }
 
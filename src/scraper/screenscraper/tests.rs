@@ -9,6 +9,11 @@ fn snes_console() -> Console {
         thegamesdb_id: Some(4),
         screenscraper_id: None,
         gamefaqs_archive_id: None,
+        extensions: vec![],
+        cores: vec![],
+        file_name: vec![],
+        headers: vec![],
+        arcade: false,
     }
 }
 
@@ -58,7 +63,7 @@ fn test_parse_game_metadata_full() {
         }
     });
 
-    let metadata = scraper.parse_game_metadata(&json).unwrap();
+    let metadata = scraper.parse_game_metadata(&json, &[]).unwrap();
 
     assert_eq!(metadata.name, "Super Mario World");
     assert_eq!(
@@ -82,7 +87,7 @@ fn test_parse_game_metadata_minimal() {
         }
     });
 
-    let metadata = scraper.parse_game_metadata(&json).unwrap();
+    let metadata = scraper.parse_game_metadata(&json, &[]).unwrap();
 
     assert_eq!(metadata.name, "Test Game");
     assert_eq!(metadata.description, None);
@@ -101,11 +106,218 @@ fn test_parse_game_metadata_missing_jeu() {
         "response": {}
     });
 
-    let result = scraper.parse_game_metadata(&json);
+    let result = scraper.parse_game_metadata(&json, &[]);
     assert!(result.is_err());
     assert!(matches!(result.unwrap_err(), ScraperError::ParseError(_)));
 }
 
+#[test]
+fn test_metadata_prefers_rom_region_and_language() {
+    let scraper = ScreenScraper::new().with_preferences(RegionPreferences {
+        regions: vec!["us".to_string(), "jp".to_string()],
+        languages: vec!["en".to_string()],
+        prefer_rom_region: true,
+    });
+    let json = serde_json::json!({
+        "jeu": {
+            "noms": [
+                {"region": "jp", "text": "Mother 3"},
+                {"region": "eu", "text": "Mother 3 (Europe)"}
+            ],
+            "synopsis": [
+                {"langue": "fr", "text": "Un RPG"},
+                {"langue": "en", "text": "An RPG"}
+            ],
+            "dates": [
+                {"region": "jp", "text": "2006-04-20"},
+                {"region": "us", "text": "2006-05-01"}
+            ],
+            "medias": [
+                {"type": "box-2D", "region": "jp", "url": "http://example.com/jp.png"},
+                {"type": "box-2D", "region": "eu", "url": "http://example.com/eu.png"}
+            ]
+        }
+    });
+
+    let metadata = scraper
+        .metadata_with_media(&json, &ParsedName::parse("Mother 3 (Europe)"))
+        .unwrap();
+    assert_eq!(metadata.name, "Mother 3 (Europe)");
+    assert_eq!(metadata.description.as_deref(), Some("An RPG"));
+    assert_eq!(metadata.release_date.as_deref(), Some("2006-05-01"));
+    assert_eq!(
+        metadata.media_url(MediaKind::BoxFront),
+        Some("http://example.com/eu.png")
+    );
+
+    let metadata = scraper
+        .metadata_with_media(&json, &ParsedName::parse("Mother 3"))
+        .unwrap();
+    assert_eq!(metadata.name, "Mother 3");
+    assert_eq!(
+        metadata.media_url(MediaKind::BoxFront),
+        Some("http://example.com/jp.png")
+    );
+}
+
+#[test]
+fn test_get_media_kinds() {
+    let json = serde_json::json!({
+        "jeu": {
+            "medias": [
+                {"type": "ss", "region": "wor", "url": "http://example.com/ss.png"},
+                {"type": "video", "url": "http://example.com/video.mp4"},
+                {"type": "box-2D", "region": "jp", "url": "http://example.com/jp.png"},
+                {"type": "box-2D", "region": "us", "url": "http://example.com/us.png"},
+                {"type": "unknown-type", "url": "http://example.com/x.png"}
+            ]
+        }
+    });
+
+    let media = ScreenScraper::get_media(&json, &["us", "wor"]);
+    assert_eq!(media.len(), 4);
+    let metadata = GameMetadata {
+        media,
+        ..ScreenScraper::new()
+            .parse_game_metadata(&json, &[])
+            .unwrap()
+    };
+    assert_eq!(
+        metadata.media_url(MediaKind::BoxFront),
+        Some("http://example.com/us.png")
+    );
+    assert_eq!(
+        metadata.media_url(MediaKind::Screenshot),
+        Some("http://example.com/ss.png")
+    );
+    assert_eq!(
+        metadata.media_url(MediaKind::Video),
+        Some("http://example.com/video.mp4")
+    );
+    assert_eq!(metadata.media_url(MediaKind::Manual), None);
+}
+
+#[test]
+fn test_candidate_from_search_result() {
+    let scraper = ScreenScraper::new();
+    let query = ParsedName::parse("Metroid Fusion (USA)");
+    let jeu = serde_json::json!({
+        "id": "3411",
+        "noms": [
+            {"region": "jp", "text": "Metroid Fusion (JP)"},
+            {"region": "us", "text": "Metroid Fusion"}
+        ],
+        "systeme": {"id": "12", "text": "Game Boy Advance"},
+        "dates": [{"region": "us", "text": "2002-11-17"}],
+        "medias": [
+            {"type": "box-2D", "region": "us", "url": "http://example.com/us.png"}
+        ]
+    });
+
+    let candidate = scraper.candidate(&jeu, &query, Some("12")).unwrap();
+    assert_eq!(candidate.id, "3411");
+    assert_eq!(candidate.title, "Metroid Fusion");
+    assert_eq!(candidate.platform.as_deref(), Some("Game Boy Advance"));
+    assert_eq!(candidate.year, Some(2002));
+    assert_eq!(
+        candidate.thumbnail_url.as_deref(),
+        Some("http://example.com/us.png")
+    );
+    assert!(candidate.confidence > 0.9);
+
+    // Searches without results return a single empty game
+    assert!(
+        scraper
+            .candidate(&serde_json::json!({}), &query, Some("12"))
+            .is_none()
+    );
+}
+
+#[test]
+fn test_best_search_result_for_translation() {
+    let scraper = ScreenScraper::new();
+    // A fan translation has no known checksums, so it is matched by its title
+    let rom = ParsedName::parse("Mother 3 (Japan) [T-En by Tomato]");
+    let jeux = vec![
+        serde_json::json!({
+            "id": "1",
+            "noms": [{"region": "jp", "text": "Mother 1+2"}],
+            "systeme": {"id": "12", "text": "Game Boy Advance"}
+        }),
+        serde_json::json!({
+            "id": "2",
+            "noms": [{"region": "jp", "text": "Mother 3"}],
+            "systeme": {"id": "12", "text": "Game Boy Advance"},
+            "synopsis": [{"langue": "en", "text": "The last Mother game."}]
+        }),
+        serde_json::json!({}),
+    ];
+
+    let (jeu, confidence) = scraper.best_search_result(jeux, &rom, "12").unwrap();
+    assert_eq!(jeu["id"], "2");
+    assert!(confidence >= ranking::MIN_CONFIDENCE);
+
+    let metadata = scraper
+        .metadata_with_media(&serde_json::json!({ "jeu": jeu }), &rom)
+        .unwrap();
+    assert_eq!(metadata.name, "Mother 3");
+    assert_eq!(
+        metadata.description.as_deref(),
+        Some("The last Mother game.")
+    );
+
+    // Unrelated results are not trusted
+    let jeux = vec![serde_json::json!({
+        "id": "3",
+        "noms": [{"region": "us", "text": "EarthBound"}],
+        "systeme": {"id": "4", "text": "Super Nintendo"}
+    })];
+    assert!(scraper.best_search_result(jeux, &rom, "12").is_none());
+}
+
+#[test]
+fn test_parse_quota() {
+    let json = serde_json::json!({
+        "response": {
+            "ssuser": {
+                "id": "collie",
+                "maxthreads": "2",
+                "maxdownloadspeed": "256",
+                "requeststoday": "19995",
+                "maxrequestsperday": "20000"
+            }
+        }
+    });
+
+    let quota = ScreenScraper::parse_quota(&json).unwrap();
+    assert_eq!(quota.max_threads, Some(2));
+    assert_eq!(quota.max_download_speed, Some(256));
+    assert_eq!(quota.remaining(), 5);
+    assert!(quota.is_exhausted());
+
+    // Anonymous responses have no user
+    assert_eq!(
+        ScreenScraper::parse_quota(&serde_json::json!({"response": {}})),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_used_up_quota_stops_requests() {
+    let scraper = ScreenScraper::new();
+    *scraper.quota.lock().unwrap() = Some(Quota {
+        requests_today: 20000,
+        max_requests_per_day: 20000,
+        ..Default::default()
+    });
+
+    // Refused before any request is sent
+    assert!(matches!(
+        scraper.get_game_metadata("3411").await,
+        Err(ScraperError::QuotaExceeded)
+    ));
+}
+
 #[test]
 fn test_get_media_url_exact_region_match() {
     let scraper = ScreenScraper::new();
@@ -299,10 +511,10 @@ async fn test_search_game_integration() {
     let scraper = ScreenScraper::new();
 
     let result = scraper
-        .search_game(
-            &PathBuf::from("Super Mario World (USA).zip"),
-            &snes_console(),
-        )
+        .search_game(&RomFile::new(
+            PathBuf::from("Super Mario World (USA).zip"),
+            snes_console(),
+        ))
         .await;
 
     match result {
@@ -326,10 +538,10 @@ async fn test_authenticated_search() {
         .unwrap();
 
     let result = scraper
-        .search_game(
-            &PathBuf::from("Super Mario World (USA).zip"),
-            &snes_console(),
-        )
+        .search_game(&RomFile::new(
+            PathBuf::from("Super Mario World (USA).zip"),
+            snes_console(),
+        ))
         .await;
 
     match result {
@@ -1,12 +1,36 @@
 use crate::console::Console;
-
-use super::{GameMetadata, MetadataScraper, ScraperError, ScraperResult};
+use crate::filename::ParsedName;
+use crate::scanner::RomFile;
+
+use super::http::HttpClient;
+use super::media::{Media, MediaKind};
+use super::preferences::RegionPreferences;
+use super::ranking::{self, Candidate};
+use super::{GameCandidate, GameMetadata, MetadataScraper, ScraperError, ScraperResult};
 use async_trait::async_trait;
+use names::NameLookups;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::warn;
+
+mod names;
 
 const THEGAMESDB_API_URL: &str = "https://api.thegamesdb.net/v1";
 
+/// Game fields returned by `Games/ByGameName` and `Games/ByGameID` on top of the defaults
+const GAME_FIELDS: &str = "players,publishers,genres,overview,rating";
+
+/// Image types served by `Games/Images`, besides box art
+const IMAGE_TYPES: &[(&str, MediaKind)] = &[
+    ("screenshot", MediaKind::Screenshot),
+    ("titlescreen", MediaKind::TitleScreen),
+    ("clearlogo", MediaKind::Logo),
+    ("fanart", MediaKind::Fanart),
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TheGamesDBConfig {
     #[serde(rename = "apiKey")]
@@ -27,7 +51,6 @@ struct GamesDBData {
 }
 
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
 struct GamesDBGame {
     id: u64,
     game_title: String,
@@ -36,6 +59,8 @@ struct GamesDBGame {
     #[serde(default)]
     platform: Option<u64>,
     #[serde(default)]
+    region_id: Option<u64>,
+    #[serde(default)]
     players: Option<u32>,
     #[serde(default)]
     overview: Option<String>,
@@ -60,7 +85,9 @@ struct GamesDBIncludes {
 
 #[derive(Debug, Deserialize)]
 struct GamesDBBoxart {
-    base_url: String,
+    /// Image URL prefixes by size,
+    /// e.g. `{"original": "https://cdn.thegamesdb.net/images/original/"}`
+    base_url: serde_json::Value,
     data: serde_json::Value,
 }
 
@@ -83,8 +110,13 @@ struct GamesDBImage {
 }
 
 pub struct TheGamesDB {
+    base_url: String,
     api_key: Option<String>,
-    client: reqwest::Client,
+    preferences: RegionPreferences,
+    media_kinds: Vec<MediaKind>,
+    names_cache: Option<PathBuf>,
+    names: OnceCell<NameLookups>,
+    http: Arc<HttpClient>,
 }
 
 impl Default for TheGamesDB {
@@ -96,90 +128,113 @@ impl Default for TheGamesDB {
 impl TheGamesDB {
     pub fn new() -> Self {
         Self {
+            base_url: THEGAMESDB_API_URL.to_string(),
             api_key: None,
-            client: reqwest::Client::new(),
+            preferences: RegionPreferences::default(),
+            media_kinds: Vec::new(),
+            names_cache: None,
+            names: OnceCell::new(),
+            http: Arc::new(HttpClient::default()),
         }
     }
 
     pub fn with_api_key(api_key: String) -> Self {
         Self {
             api_key: Some(api_key),
-            client: reqwest::Client::new(),
+            ..Self::new()
         }
     }
 
+    /// Use another API server, e.g. a local stand-in for tests
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Share an HTTP client, and its rate limits, with other scrapers
+    pub fn with_http_client(mut self, http: Arc<HttpClient>) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Set the preferred regions used to rank releases of the same game
+    pub fn with_preferences(mut self, preferences: RegionPreferences) -> Self {
+        self.preferences = preferences;
+        self
+    }
+
+    /// Set the media kinds to download, so that screenshots, fan art, logos and title screens
+    /// are only requested when wanted
+    pub fn with_media_kinds(mut self, media_kinds: Vec<MediaKind>) -> Self {
+        self.media_kinds = media_kinds;
+        self
+    }
+
+    /// Cache developer, publisher and genre names in a file, e.g. `.collie/thegamesdb.json`
+    pub fn with_names_cache(mut self, path: PathBuf) -> Self {
+        self.names_cache = Some(path);
+        self
+    }
+
     fn get_api_key(&self) -> ScraperResult<&str> {
         self.api_key
             .as_deref()
             .ok_or(ScraperError::AuthenticationFailed)
     }
 
-    /// Platform ID mapping for common systems
-    /// See: https://api.thegamesdb.net/v1/Platforms
-    pub fn map_screenscraper_to_thegamesdb(screenscraper_id: &str) -> Option<&'static str> {
-        match screenscraper_id {
-            "3" => Some("7"),   // NES
-            "4" => Some("6"),   // SNES
-            "1" => Some("36"),  // Sega Genesis
-            "2" => Some("35"),  // Sega Master System
-            "9" => Some("4"),   // Game Boy
-            "10" => Some("5"),  // Game Boy Color
-            "12" => Some("12"), // Game Boy Advance
-            "15" => Some("8"),  // Nintendo DS
-            "57" => Some("10"), // PlayStation
-            "58" => Some("11"), // PlayStation 2
-            _ => None,
+    /// Region names for a TheGamesDB region ID
+    /// See: https://api.thegamesdb.net/v1/Regions
+    fn map_region(region_id: u64) -> &'static [&'static str] {
+        match region_id {
+            1 => &["USA", "Japan"], // NTSC
+            2 => &["USA"],          // NTSC-U
+            3 => &["Europe"],       // PAL
+            4 => &["Japan"],        // NTSC-J
+            5 => &["Korea"],        // NTSC-K
+            _ => &[],
         }
     }
-}
 
-#[async_trait]
-impl MetadataScraper for TheGamesDB {
-    fn name(&self) -> &'static str {
-        "TheGamesDB"
-    }
-
-    async fn authenticate(&mut self, username: &str, _password: &str) -> ScraperResult<()> {
-        // TheGamesDB uses API key, not username/password
-        // We'll treat the username as the API key
-        self.api_key = Some(username.to_string());
-        Ok(())
+    /// Reduce a search result to the fields used for ranking
+    fn candidate(game: &GamesDBGame, platform_id: i32) -> Candidate {
+        Candidate {
+            title: game.game_title.clone(),
+            platform_match: game
+                .platform
+                .map(|platform| i64::try_from(platform) == Ok(i64::from(platform_id))),
+            regions: game
+                .region_id
+                .map(Self::map_region)
+                .unwrap_or_default()
+                .iter()
+                .map(|region| region.to_string())
+                .collect(),
+            year: game.release_date.as_deref().and_then(ranking::parse_year),
+        }
     }
 
-    async fn search_game(&self, path: &Path, console: &Console) -> ScraperResult<GameMetadata> {
-        let Some(console_id) = console.thegamesdb_id.as_ref() else {
+    /// Request the games matching a title on the console's platform
+    async fn search_games(
+        &self,
+        title: &str,
+        console: &Console,
+    ) -> ScraperResult<(GamesDBResponse, i32)> {
+        let Some(platform_id) = console.thegamesdb_id else {
             return Err(ScraperError::PlatformNotSupported);
         };
 
         let api_key = self.get_api_key()?;
 
-        // Map ScreenScraper console ID to TheGamesDB platform ID
-        let platform_id = Self::map_screenscraper_to_thegamesdb(&console_id.to_string());
-
-        let game_name = path
-            .file_stem()
-            .and_then(|n| n.to_str())
-            .ok_or(ScraperError::GameNotFound)?;
-
-        let mut url = format!(
-            "{}/Games/ByGameName?apikey={}&name={}",
-            THEGAMESDB_API_URL,
+        let url = format!(
+            "{}/Games/ByGameName?apikey={}&name={}&filter[platform]={}&fields={}&include=boxart",
+            self.base_url,
             api_key,
-            urlencoding::encode(game_name)
+            urlencoding::encode(title),
+            platform_id,
+            GAME_FIELDS
         );
 
-        if let Some(platform) = platform_id {
-            url.push_str(&format!("&filter[platform]={}", platform));
-        }
-
-        url.push_str("&include=boxart");
-
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| ScraperError::Network(e.to_string()))?;
+        let response = self.http.get(&url).await?;
 
         if response.status() == 403 {
             return Err(ScraperError::RateLimitExceeded);
@@ -197,119 +252,344 @@ impl MetadataScraper for TheGamesDB {
             .await
             .map_err(|e| ScraperError::ParseError(e.to_string()))?;
 
-        if data.data.games.is_empty() {
-            return Err(ScraperError::GameNotFound);
+        Ok((data, platform_id))
+    }
+
+    /// Add the preferred regions after the ROM's own, so that releases from them rank next
+    fn ranking_query(&self, query: &ParsedName) -> ParsedName {
+        let mut query = query.clone();
+        for region in self.preferences.region_names_for(&query) {
+            if !query.regions.contains(&region) {
+                query.regions.push(region);
+            }
         }
+        query
+    }
+
+    /// Get the box art of a game from a response's boxart include, front covers first
+    fn boxart_media(data: &GamesDBResponse, game_id: u64) -> Vec<Media> {
+        let Some(boxart) = data.include.as_ref().and_then(|i| i.boxart.as_ref()) else {
+            return Vec::new();
+        };
+        let Some(base_url) = boxart
+            .base_url
+            .get("original")
+            .unwrap_or(&boxart.base_url)
+            .as_str()
+        else {
+            return Vec::new();
+        };
+        let images = boxart
+            .data
+            .get(game_id.to_string())
+            .and_then(|images| images.as_array());
+
+        let mut media: Vec<Media> = images
+            .into_iter()
+            .flatten()
+            .filter_map(|image| {
+                let kind = match image.get("side").and_then(|s| s.as_str()) {
+                    Some("back") => MediaKind::BoxBack,
+                    _ => MediaKind::BoxFront,
+                };
+                let filename = image.get("filename")?.as_str()?;
+                Some(Media {
+                    kind,
+                    region: None,
+                    url: format!("{}{}", base_url, filename),
+                })
+            })
+            .collect();
+        media.sort_by_key(|m| m.kind != MediaKind::BoxFront);
+        media
+    }
 
+    /// Get the screenshots, fan art, logos and title screens in a `Games/Images` response
+    fn parse_images(json: &serde_json::Value, game_id: u64) -> Vec<Media> {
+        let data = json.get("data");
         let base_url = data
-            .include
-            .as_ref()
-            .and_then(|i| i.boxart.as_ref())
-            .map(|b| b.base_url.clone())
+            .and_then(|d| d.get("base_url"))
+            .and_then(|b| b.get("original"))
+            .and_then(|o| o.as_str())
             .unwrap_or_default();
 
-        let Some(game) = data.data.games.into_iter().next() else {
-            return Err(ScraperError::GameNotFound);
+        data.and_then(|d| d.get("images"))
+            .and_then(|images| images.get(game_id.to_string()))
+            .and_then(|images| images.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|image| {
+                let image_type = image.get("type")?.as_str()?;
+                let (_, kind) = IMAGE_TYPES.iter().find(|(t, _)| *t == image_type)?;
+                let filename = image.get("filename")?.as_str()?;
+                Some(Media {
+                    kind: *kind,
+                    region: None,
+                    url: format!("{}{}", base_url, filename),
+                })
+            })
+            .collect()
+    }
+
+    /// Request the images other than box art, if any of their kinds are wanted
+    async fn extra_media(&self, game_id: u64) -> Vec<Media> {
+        let types: Vec<&str> = IMAGE_TYPES
+            .iter()
+            .filter(|(_, kind)| self.media_kinds.contains(kind))
+            .map(|(image_type, _)| *image_type)
+            .collect();
+        if types.is_empty() {
+            return Vec::new();
+        }
+        let Ok(api_key) = self.get_api_key() else {
+            return Vec::new();
+        };
+
+        let url = format!(
+            "{}/Games/Images?apikey={}&games_id={}&filter[type]={}",
+            self.base_url,
+            api_key,
+            game_id,
+            types.join(",")
+        );
+        let response = match self.http.get(&url).await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                warn!("TheGamesDB images request failed: {}", response.status());
+                return Vec::new();
+            }
+            Err(e) => {
+                warn!("TheGamesDB images request failed: {}", e);
+                return Vec::new();
+            }
         };
 
-        let mut metadata = GameMetadata {
+        match response.json::<serde_json::Value>().await {
+            Ok(json) => Self::parse_images(&json, game_id),
+            Err(e) => {
+                warn!("Failed to parse TheGamesDB images: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Request a list of names by ID, e.g. `Developers`
+    async fn fetch_names(&self, endpoint: &str, key: &str) -> ScraperResult<HashMap<u64, String>> {
+        let api_key = self.get_api_key()?;
+        let url = format!("{}/{}?apikey={}", self.base_url, endpoint, api_key);
+
+        let response = self.http.get(&url).await?;
+
+        if response.status() == 403 {
+            return Err(ScraperError::RateLimitExceeded);
+        }
+
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| ScraperError::ParseError(e.to_string()))?;
+
+        Ok(NameLookups::parse_list(&json, key))
+    }
+
+    /// Get the developer, publisher and genre names, fetched once and cached
+    async fn names(&self) -> &NameLookups {
+        self.names
+            .get_or_init(|| async {
+                if let Some(names) = self.names_cache.as_deref().and_then(NameLookups::load) {
+                    return names;
+                }
+
+                let names = async {
+                    Ok::<_, ScraperError>(NameLookups {
+                        developers: self.fetch_names("Developers", "developers").await?,
+                        publishers: self.fetch_names("Publishers", "publishers").await?,
+                        genres: self.fetch_names("Genres", "genres").await?,
+                    })
+                };
+                match names.await {
+                    Ok(names) => {
+                        if let Some(path) = &self.names_cache
+                            && let Err(e) = names.save(path)
+                        {
+                            warn!("Failed to cache TheGamesDB names: {}", e);
+                        }
+                        names
+                    }
+                    Err(e) => {
+                        warn!("Failed to fetch TheGamesDB names: {}", e);
+                        NameLookups::default()
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Build the metadata of a game from a search or lookup response
+    async fn game_metadata(
+        &self,
+        game: GamesDBGame,
+        data: &GamesDBResponse,
+        confidence: Option<f32>,
+    ) -> GameMetadata {
+        let mut media = Self::boxart_media(data, game.id);
+        media.extend(self.extra_media(game.id).await);
+
+        let has_names = [&game.developers, &game.publishers, &game.genres]
+            .iter()
+            .any(|ids| ids.as_ref().is_some_and(|ids| !ids.is_empty()));
+        let (developer, publisher, genre) = if has_names {
+            let names = self.names().await;
+            (
+                NameLookups::resolve(&names.developers, game.developers.as_deref()),
+                NameLookups::resolve(&names.publishers, game.publishers.as_deref()),
+                NameLookups::resolve(&names.genres, game.genres.as_deref()),
+            )
+        } else {
+            (None, None, None)
+        };
+
+        GameMetadata {
             name: game.game_title,
             description: game.overview,
             release_date: game.release_date,
-            developer: None,
-            publisher: None,
-            genre: None,
+            developer,
+            publisher,
+            genre,
             players: game.players.map(|p| p.to_string()),
             rating: game.rating.and_then(|r| r.parse::<f32>().ok()),
-            image_url: None,
+            media,
             thumbnail_url: None,
-        };
+            confidence,
+        }
+    }
 
-        // Try to get boxart URL
-        if let Some(ref includes) = data.include
-            && let Some(ref boxart) = includes.boxart
-        {
-            // Try to find boxart for this game in the data
-            if let Some(game_images) = boxart.data.get(game.id.to_string())
-                && let Some(images) = game_images.as_array()
-            {
-                for image in images {
-                    if let Some(filename) = image.get("filename").and_then(|f| f.as_str())
-                        && image.get("side").and_then(|s| s.as_str()) == Some("front")
-                    {
-                        metadata.image_url = Some(format!("{}{}", base_url, filename));
-                        break;
-                    }
-                }
-                // If no front cover, use any boxart
-                if metadata.image_url.is_none()
-                    && let Some(image) = images.first()
-                    && let Some(filename) = image.get("filename").and_then(|f| f.as_str())
-                {
-                    metadata.image_url = Some(format!("{}{}", base_url, filename));
-                }
-            }
+    /// Search for a game by the title parsed from its file name, keeping the best ranked result
+    async fn search_by_title(
+        &self,
+        query: &ParsedName,
+        console: &Console,
+    ) -> ScraperResult<GameMetadata> {
+        let (mut data, platform_id) = self.search_games(&query.title, console).await?;
+
+        if data.data.games.is_empty() {
+            return Err(ScraperError::GameNotFound);
         }
 
-        Ok(metadata)
+        // Releases from the ROM's own region rank first, then the preferred regions
+        let query = self.ranking_query(query);
+        let games = std::mem::take(&mut data.data.games);
+        let Some((game, confidence)) = ranking::best_match(
+            &query,
+            games.into_iter().map(|game| {
+                let candidate = Self::candidate(&game, platform_id);
+                (game, candidate)
+            }),
+        ) else {
+            return Err(ScraperError::GameNotFound);
+        };
+
+        Ok(self.game_metadata(game, &data, Some(confidence)).await)
+    }
+}
+
+#[async_trait]
+impl MetadataScraper for TheGamesDB {
+    fn name(&self) -> &'static str {
+        "TheGamesDB"
+    }
+
+    async fn authenticate(&mut self, username: &str, _password: &str) -> ScraperResult<()> {
+        // TheGamesDB uses API key, not username/password
+        // We'll treat the username as the API key
+        self.api_key = Some(username.to_string());
+        Ok(())
+    }
+
+    async fn search_game(&self, rom: &RomFile) -> ScraperResult<GameMetadata> {
+        self.search_by_title(&rom.parsed_name, &rom.console).await
+    }
+
+    async fn search_game_by_name(&self, name: &str, rom: &RomFile) -> ScraperResult<GameMetadata> {
+        self.search_by_title(&ParsedName::parse(name), &rom.console)
+            .await
+    }
+
+    async fn search_candidates(
+        &self,
+        name: &str,
+        console: &Console,
+    ) -> ScraperResult<Vec<GameCandidate>> {
+        let query = ParsedName::parse(name);
+        let (mut data, platform_id) = match self.search_games(&query.title, console).await {
+            Ok(result) => result,
+            Err(ScraperError::GameNotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let query = self.ranking_query(&query);
+        let games = std::mem::take(&mut data.data.games);
+        let ranked = ranking::rank(
+            &query,
+            games.into_iter().map(|game| {
+                let candidate = Self::candidate(&game, platform_id);
+                ((game, candidate.clone()), candidate)
+            }),
+        );
+
+        Ok(ranked
+            .into_iter()
+            .map(|((game, candidate), confidence)| GameCandidate {
+                id: game.id.to_string(),
+                thumbnail_url: Self::boxart_media(&data, game.id)
+                    .into_iter()
+                    .next()
+                    .map(|media| media.url),
+                platform: (candidate.platform_match == Some(true)).then(|| console.name.clone()),
+                year: candidate.year,
+                title: game.game_title,
+                confidence,
+            })
+            .collect())
     }
 
     async fn get_game_metadata(&self, game_id: &str) -> ScraperResult<GameMetadata> {
         let api_key = self.get_api_key()?;
 
         let url = format!(
-            "{}/Games/ByGameID?apikey={}&id={}&include=boxart",
-            THEGAMESDB_API_URL, api_key, game_id
+            "{}/Games/ByGameID?apikey={}&id={}&fields={}&include=boxart",
+            self.base_url, api_key, game_id, GAME_FIELDS
         );
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| ScraperError::Network(e.to_string()))?;
+        let response = self.http.get(&url).await?;
 
         if response.status() == 403 {
             return Err(ScraperError::RateLimitExceeded);
         }
 
-        let data: GamesDBResponse = response
+        let mut data: GamesDBResponse = response
             .json()
             .await
             .map_err(|e| ScraperError::ParseError(e.to_string()))?;
 
-        let game = data.data.games.first().ok_or(ScraperError::GameNotFound)?;
+        let game = data.data.games.pop().ok_or(ScraperError::GameNotFound)?;
 
-        Ok(GameMetadata {
-            name: game.game_title.clone(),
-            description: game.overview.clone(),
-            release_date: game.release_date.clone(),
-            developer: None,
-            publisher: None,
-            genre: None,
-            players: game.players.map(|p| p.to_string()),
-            rating: game.rating.clone().and_then(|r| r.parse::<f32>().ok()),
-            image_url: None,
-            thumbnail_url: None,
-        })
+        Ok(self.game_metadata(game, &data, None).await)
     }
 
     async fn download_image(&self, url: &str, destination: &Path) -> ScraperResult<()> {
-        let response = self
-            .client
-            .get(url)
-            .send()
-            .await
-            .map_err(|e| ScraperError::Network(e.to_string()))?;
-
-        let bytes = response
-            .bytes()
+        self.http
+            .download(url, MediaKind::BoxFront, destination)
             .await
-            .map_err(|e| ScraperError::Network(e.to_string()))?;
-
-        tokio::fs::write(destination, bytes).await?;
+    }
 
-        Ok(())
+    async fn download_media(
+        &self,
+        url: &str,
+        kind: MediaKind,
+        destination: &Path,
+    ) -> ScraperResult<()> {
+        self.http.download(url, kind, destination).await
     }
 }
 
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Cached lists are fetched again after this long, so that new IDs resolve
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Developer, publisher and genre names by ID, from `/Developers`, `/Publishers` and `/Genres`
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NameLookups {
+    pub developers: HashMap<u64, String>,
+    pub publishers: HashMap<u64, String>,
+    pub genres: HashMap<u64, String>,
+}
+
+impl NameLookups {
+    /// Load cached lists, unless they are missing, unreadable or older than `MAX_AGE`
+    pub fn load(path: &Path) -> Option<Self> {
+        let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+        if SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default()
+            > MAX_AGE
+        {
+            return None;
+        }
+        serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Parse a list response such as `{"data": {"developers": {"1": {"id": 1, "name": "..."}}}}`
+    pub fn parse_list(json: &serde_json::Value, key: &str) -> HashMap<u64, String> {
+        json.get("data")
+            .and_then(|data| data.get(key))
+            .and_then(|list| list.as_object())
+            .into_iter()
+            .flatten()
+            .filter_map(|(id, entry)| {
+                let id = entry
+                    .get("id")
+                    .and_then(|id| id.as_u64())
+                    .or_else(|| id.parse().ok())?;
+                let name = entry.get("name")?.as_str()?;
+                Some((id, name.to_string()))
+            })
+            .collect()
+    }
+
+    /// Join the names of a list of IDs, skipping unknown ones
+    pub fn resolve(names: &HashMap<u64, String>, ids: Option<&[u64]>) -> Option<String> {
+        let resolved: Vec<&str> = ids?
+            .iter()
+            .filter_map(|id| names.get(id))
+            .map(String::as_str)
+            .collect();
+        (!resolved.is_empty()).then(|| resolved.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list_and_resolve() {
+        let json = serde_json::json!({
+            "code": 200,
+            "data": {
+                "count": 3,
+                "developers": {
+                    "1": { "id": 1, "name": "Nintendo R&D1" },
+                    "8": { "id": 8, "name": "Intelligent Systems" },
+                    "9": { "id": 9 }
+                }
+            }
+        });
+
+        let developers = NameLookups::parse_list(&json, "developers");
+        assert_eq!(developers.len(), 2);
+        assert!(NameLookups::parse_list(&json, "genres").is_empty());
+
+        assert_eq!(
+            NameLookups::resolve(&developers, Some(&[1, 8, 42])),
+            Some("Nintendo R&D1, Intelligent Systems".to_string())
+        );
+        assert_eq!(NameLookups::resolve(&developers, Some(&[42])), None);
+        assert_eq!(NameLookups::resolve(&developers, None), None);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = std::env::temp_dir().join("collie_test_thegamesdb_names");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let path = temp_dir.join(".collie").join("thegamesdb.json");
+
+        assert_eq!(NameLookups::load(&path), None);
+
+        let mut names = NameLookups::default();
+        names.genres.insert(1, "Action".to_string());
+        names.save(&path).unwrap();
+        assert_eq!(NameLookups::load(&path), Some(names));
+
+        // Clean up
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -6,9 +6,14 @@ fn snes_console() -> Console {
     Console {
         name: "SNES".to_owned(),
         patterns: vec!["SNES".to_owned(), "SFC".to_owned()],
-        thegamesdb_id: Some(4),
+        thegamesdb_id: Some(6),
         screenscraper_id: None,
         gamefaqs_archive_id: None,
+        extensions: vec![],
+        cores: vec![],
+        file_name: vec![],
+        headers: vec![],
+        arcade: false,
     }
 }
 
@@ -41,7 +46,10 @@ async fn test_search_without_api_key() {
     let scraper = TheGamesDB::new();
 
     let result = scraper
-        .search_game(&PathBuf::from("Super Mario (USA).zip"), &snes_console())
+        .search_game(&RomFile::new(
+            PathBuf::from("Super Mario (USA).zip"),
+            snes_console(),
+        ))
         .await;
 
     assert!(result.is_err());
@@ -74,6 +82,23 @@ async fn test_get_api_key_when_missing() {
     ));
 }
 
+#[test]
+fn test_candidate_from_search_result() {
+    let game: GamesDBGame = serde_json::from_str(
+        r#"{"id": 113, "game_title": "Metroid", "release_date": "1987-08-15", "platform": 7, "region_id": 2}"#,
+    )
+    .unwrap();
+
+    let candidate = TheGamesDB::candidate(&game, 7);
+    assert_eq!(candidate.title, "Metroid");
+    assert_eq!(candidate.platform_match, Some(true));
+    assert_eq!(candidate.regions, ["USA"]);
+    assert_eq!(candidate.year, Some(1987));
+
+    let candidate = TheGamesDB::candidate(&game, 6);
+    assert_eq!(candidate.platform_match, Some(false));
+}
+
 // Integration tests - these hit the real API and are ignored by default
 #[tokio::test]
 #[ignore]
@@ -86,16 +111,16 @@ async fn test_search_game_integration() {
 
     // Search for Super Mario World on SNES (platform 6)
     let result = scraper
-        .search_game(
-            &PathBuf::from("Super Mario World (USA).zip"),
-            &snes_console(),
-        )
+        .search_game(&RomFile::new(
+            PathBuf::from("Super Mario World (USA).zip"),
+            snes_console(),
+        ))
         .await;
 
     match result {
         Ok(game) => {
             println!("  - {}", game.name);
-            if let Some(ref url) = game.image_url {
+            if let Some(url) = game.media_url(MediaKind::BoxFront) {
                 println!("    Image: {}", url);
             }
             assert!(game.name.contains("Mario"));
@@ -105,3 +130,58 @@ async fn test_search_game_integration() {
         }
     }
 }
+
+#[test]
+fn test_boxart_media_lists_front_first() {
+    let data: GamesDBResponse = serde_json::from_value(serde_json::json!({
+        "data": { "games": [] },
+        "include": {
+            "boxart": {
+                "base_url": { "original": "https://cdn.thegamesdb.net/images/original/" },
+                "data": {
+                    "42": [
+                        { "id": 1, "type": "boxart", "side": "back", "filename": "boxart/back/42-1.jpg" },
+                        { "id": 2, "type": "boxart", "side": "front", "filename": "boxart/front/42-1.jpg" }
+                    ]
+                }
+            }
+        }
+    }))
+    .unwrap();
+
+    let media = TheGamesDB::boxart_media(&data, 42);
+    assert_eq!(media.len(), 2);
+    assert_eq!(media[0].kind, MediaKind::BoxFront);
+    assert_eq!(
+        media[0].url,
+        "https://cdn.thegamesdb.net/images/original/boxart/front/42-1.jpg"
+    );
+    assert_eq!(media[1].kind, MediaKind::BoxBack);
+    assert!(TheGamesDB::boxart_media(&data, 43).is_empty());
+}
+
+#[test]
+fn test_parse_images() {
+    let json = serde_json::json!({
+        "data": {
+            "count": 3,
+            "base_url": { "original": "https://cdn.thegamesdb.net/images/original/" },
+            "images": {
+                "42": [
+                    { "id": 1, "type": "screenshot", "side": null, "filename": "screenshots/42-1.jpg" },
+                    { "id": 2, "type": "clearlogo", "side": null, "filename": "clearlogo/42.png" },
+                    { "id": 3, "type": "banner", "side": null, "filename": "graphical/42-g.jpg" }
+                ]
+            }
+        }
+    });
+
+    let media = TheGamesDB::parse_images(&json, 42);
+    assert_eq!(media.len(), 2);
+    assert_eq!(media[0].kind, MediaKind::Screenshot);
+    assert_eq!(
+        media[0].url,
+        "https://cdn.thegamesdb.net/images/original/screenshots/42-1.jpg"
+    );
+    assert_eq!(media[1].kind, MediaKind::Logo);
+}
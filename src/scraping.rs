@@ -1,20 +1,76 @@
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::sync::mpsc::UnboundedSender;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 use crate::backoff::BackoffState;
+use crate::hashing::HashIndex;
 use crate::image::resize_image;
+use crate::overrides::Overrides;
 use crate::progress::{GameData, ProgressUpdate, ScrapeStatus, ScrapingProgress, send_progress};
 use crate::scanner;
+use crate::scraper::media::MediaKind;
 use crate::scraper::{GuidesScraper, MetadataScraper};
 use crate::storage::load_game_data;
 
+/// Where images and guides are placed for ROMs found in console subfolders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MediaLayout {
+    /// Mirror the subfolder tree under the console's media folder, e.g. `GBA/Imgs/RPG/x.png`
+    #[default]
+    Mirrored,
+    /// Put a media folder inside each subfolder, e.g. `GBA/RPG/Imgs/x.png`
+    Adjacent,
+}
+
 pub struct ScrapingConfig {
     pub roms_path: PathBuf,
+    /// User console definitions, defaults to `.collie/consoles.toml` in the ROMs folder
+    pub consoles_path: Option<PathBuf>,
     pub images_folder: String,
     pub guides_folder: String,
     pub box_art_width: Option<u32>,
     pub skip_cache: bool,
+    pub max_scan_depth: usize,
+    /// Identify loose ROMs in folders matching no console by file name or header
+    pub detect_unknown_folders: bool,
+    pub media_layout: MediaLayout,
+    /// Write an .m3u playlist for each multi-disc game
+    pub generate_m3u: bool,
+    /// Move the discs of generated playlists into a hidden folder
+    pub hide_discs: bool,
+    /// Checksums of previously hashed ROMs, used to match against imported DATs
+    pub hash_index: Arc<HashIndex>,
+    /// Game IDs pinned by hand in `.collie/overrides.toml`
+    pub overrides: Overrides,
+    /// Media kind saved as the game's main image, falling back to the front of the box
+    pub box_art: MediaKind,
+    /// Other media kinds to download, each into its own folder inside the images folder
+    pub media_kinds: Vec<MediaKind>,
+}
+
+/// Get the folder holding a ROM's media of the given kind (images or guides)
+pub fn media_dir(rom: &scanner::RomFile, folder: impl AsRef<Path>, layout: MediaLayout) -> PathBuf {
+    match layout {
+        MediaLayout::Mirrored => rom.console_dir().join(folder).join(rom.relative_dir()),
+        MediaLayout::Adjacent => rom.console_dir().join(rom.relative_dir()).join(folder),
+    }
+}
+
+/// Get the path of a ROM's media of a kind other than the main image, e.g. `Imgs/videos/x.mp4`
+pub fn media_path(
+    rom: &scanner::RomFile,
+    images_folder: &str,
+    layout: MediaLayout,
+    kind: MediaKind,
+) -> PathBuf {
+    media_dir(rom, Path::new(images_folder).join(kind.folder()), layout).join(format!(
+        "{}.{}",
+        rom.name_no_extension,
+        kind.extension()
+    ))
 }
 
 /// Scrape game metadata from multiple scrapers with fallback
@@ -27,15 +83,27 @@ pub async fn scrape_game_metadata(
     progress_tx: &UnboundedSender<ProgressUpdate>,
     backoff: &mut BackoffState,
 ) -> bool {
-    let console_dir = rom.path.parent().unwrap();
-    let image_path = console_dir
-        .join(&config.images_folder)
+    let image_path = media_dir(rom, &config.images_folder, config.media_layout)
         .join(format!("{}.png", rom.name_no_extension));
 
+    // ROMs whose pinned game IDs changed since the last scrape are scraped again, so that a
+    // new pin replaces the old match
+    let rom_key = Overrides::rom_key(&config.roms_path, rom);
+    game_data.metadata.pinned_ids = config.overrides.game_ids(&rom_key);
+
     // Check if image already exists (skip this check if skip_cache is enabled)
-    if !config.skip_cache && !scrapers.is_empty() && image_path.exists() {
+    let cached = !config.skip_cache && !scrapers.is_empty() && image_path.exists();
+    let existing = cached
+        .then(|| load_game_data(&config.roms_path, rom))
+        .flatten();
+    let pins_changed = match &existing {
+        Some(existing) => existing.metadata.pinned_ids != game_data.metadata.pinned_ids,
+        None => !game_data.metadata.pinned_ids.is_empty(),
+    };
+
+    if cached && !pins_changed {
         // Load existing game data to populate metadata
-        if let Some(existing) = load_game_data(&config.roms_path, rom) {
+        if let Some(existing) = existing {
             game_data.metadata.name = existing.metadata.name;
             game_data.metadata.developer = existing.metadata.developer;
             game_data.metadata.publisher = existing.metadata.publisher;
@@ -82,7 +150,21 @@ pub async fn scrape_game_metadata(
     let mut all_not_found = true; // Track if all scrapers returned GameNotFound
     let mut tried_any = false;
 
-    for scraper in scrapers {
+    // Backends with a pinned game ID go first, so that an automatic match on another backend
+    // never hides the pin
+    let is_pinned = |scraper: &dyn MetadataScraper| {
+        config.overrides.game_id(&rom_key, scraper.name()).is_some()
+    };
+    let ordered = scrapers
+        .iter()
+        .filter(|scraper| is_pinned(scraper.as_ref()))
+        .chain(
+            scrapers
+                .iter()
+                .filter(|scraper| !is_pinned(scraper.as_ref())),
+        );
+
+    for scraper in ordered {
         send_progress(
             progress_tx,
             progress,
@@ -92,7 +174,30 @@ pub async fn scrape_game_metadata(
 
         tried_any = true;
 
-        let metadata = match scraper.search_game(&rom.path, &rom.console).await {
+        // ROMs identified by a DAT or arcade list are searched by their canonical name
+        let search = async {
+            match rom.canonical_name() {
+                Some(name) => scraper.search_game_by_name(name, rom).await,
+                None => scraper.search_game(rom).await,
+            }
+        };
+
+        // Pinned game IDs are fetched directly, and discs with a serial are looked up by it
+        // first, falling back to a regular search
+        let result = if let Some(game_id) = config.overrides.game_id(&rom_key, scraper.name()) {
+            info!("Using pinned {} game ID {}", scraper.name(), game_id);
+            scraper.get_game_metadata(game_id).await
+        } else {
+            match &rom.serial {
+                Some(serial) => match scraper.search_game_by_serial(serial, rom).await {
+                    Err(crate::scraper::ScraperError::GameNotFound) => search.await,
+                    result => result,
+                },
+                None => search.await,
+            }
+        };
+
+        let metadata = match result {
             Ok(metadata) => {
                 // Success - reset backoff for this scraper
                 backoff.reset(scraper.name());
@@ -141,6 +246,7 @@ pub async fn scrape_game_metadata(
         game_data.metadata.genre = metadata.genre.clone();
         game_data.metadata.release_date = metadata.release_date.clone();
         game_data.metadata.rating = metadata.rating.map(|r| format!("{:.1}", r));
+        game_data.metadata.confidence = metadata.confidence;
 
         send_progress(
             progress_tx,
@@ -149,7 +255,20 @@ pub async fn scrape_game_metadata(
             Some(game_data.clone()),
         );
 
-        if let Some(ref image_url) = metadata.image_url {
+        download_media(
+            scraper.as_ref(),
+            &metadata,
+            rom,
+            config,
+            progress,
+            progress_tx,
+        )
+        .await;
+
+        let image_url = metadata
+            .media_url(config.box_art)
+            .or_else(|| metadata.media_url(MediaKind::BoxFront));
+        if let Some(image_url) = image_url {
             if let Some(parent) = image_path.parent()
                 && let Err(e) = std::fs::create_dir_all(parent)
             {
@@ -221,6 +340,48 @@ pub async fn scrape_game_metadata(
     false
 }
 
+/// Download the configured media kinds other than the main image
+async fn download_media(
+    scraper: &dyn MetadataScraper,
+    metadata: &crate::scraper::GameMetadata,
+    rom: &scanner::RomFile,
+    config: &ScrapingConfig,
+    progress: &ScrapingProgress,
+    progress_tx: &UnboundedSender<ProgressUpdate>,
+) {
+    for &kind in &config.media_kinds {
+        let Some(url) = metadata.media_url(kind) else {
+            continue;
+        };
+        let path = media_path(rom, &config.images_folder, config.media_layout, kind);
+        if !config.skip_cache && path.exists() {
+            continue;
+        }
+
+        if let Some(parent) = path.parent()
+            && let Err(e) = std::fs::create_dir_all(parent)
+        {
+            error!("Failed to create directory: {}", e);
+            continue;
+        }
+
+        match scraper.download_media(url, kind, &path).await {
+            Ok(_) => send_progress(
+                progress_tx,
+                progress,
+                format!("Downloaded {}", kind.folder()),
+                None,
+            ),
+            Err(e) => warn!(
+                "Failed to download {} for {}: {}",
+                kind.folder(),
+                rom.name,
+                e
+            ),
+        }
+    }
+}
+
 /// Scrape game guides using a guides scraper
 pub async fn scrape_game_guides(
     guides_scrapers: &[Box<dyn GuidesScraper>],
@@ -231,10 +392,8 @@ pub async fn scrape_game_guides(
     progress_tx: &UnboundedSender<ProgressUpdate>,
     backoff: &mut BackoffState,
 ) {
-    let console_dir = rom.path.parent().unwrap();
-    let guides_dir = console_dir
-        .join(&config.guides_folder)
-        .join(&rom.name_no_extension);
+    let guides_dir =
+        media_dir(rom, &config.guides_folder, config.media_layout).join(&rom.name_no_extension);
 
     // Check if guides already exist (skip this check if skip_cache is enabled)
     let guides_exist = !config.skip_cache
@@ -280,10 +439,19 @@ pub async fn scrape_game_guides(
     );
 
     for guides_scraper in guides_scrapers {
-        match guides_scraper
-            .search_game_guides(&rom.path, &rom.console)
-            .await
-        {
+        let result = match rom.canonical_name() {
+            Some(name) => {
+                guides_scraper
+                    .search_game_guides_by_name(name, &rom.path, &rom.console)
+                    .await
+            }
+            None => {
+                guides_scraper
+                    .search_game_guides(&rom.path, &rom.console)
+                    .await
+            }
+        };
+        match result {
             Ok(guide_paths) if !guide_paths.is_empty() => {
                 // Success - reset backoff for this scraper
                 backoff.reset(guides_scraper.name());
@@ -309,12 +477,10 @@ pub async fn scrape_game_guides(
                     let guide_dest = guides_dir.join(guide_filename);
                     match guides_scraper.download_guide(guide_path, &guide_dest).await {
                         Ok(_) => {
-                            // Use PathBuf::join to ensure correct separator for the platform
-                            let rel_path = PathBuf::from(".")
-                                .join(&config.guides_folder)
-                                .join(&rom.name_no_extension)
-                                .join(guide_filename);
-                            downloaded_guides.push(rel_path);
+                            // Path relative to the console folder, as used by gamelist.xml
+                            if let Ok(rel_to_console) = guide_dest.strip_prefix(rom.console_dir()) {
+                                downloaded_guides.push(PathBuf::from(".").join(rel_to_console));
+                            }
                         }
                         Err(e) => {
                             send_progress(
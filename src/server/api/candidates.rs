@@ -0,0 +1,144 @@
+use axum::{Json, extract::State, http::StatusCode};
+use collie::console::{Console, ConsolesConfig};
+use collie::hashing::HashIndex;
+use collie::scraper::GameCandidate;
+use collie::scraper::preferences::RegionPreferences;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::server::api::overrides::{OverridesResponse, SetOverrideRequest, set_override};
+use crate::server::api::scrape::{MetadataBackendConfigs, build_metadata_scrapers};
+use crate::server::state::AppState;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CandidatesRequest {
+    /// ROM path relative to the ROMs folder, e.g. `GBA/Metroid Fusion (USA).gba`
+    pub rom_path: String,
+    /// Name to search for instead of the file name
+    pub name: Option<String>,
+    #[serde(default)]
+    pub region_preferences: RegionPreferences,
+    pub metadata_backends: MetadataBackendConfigs,
+}
+
+/// The candidates found by one backend
+#[derive(Debug, Serialize)]
+pub struct BackendCandidates {
+    pub backend: String,
+    pub candidates: Vec<GameCandidate>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CandidatesResponse {
+    pub success: bool,
+    pub message: String,
+    pub results: Vec<BackendCandidates>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChooseCandidateRequest {
+    pub rom_path: String,
+    pub backend: String,
+    pub game_id: String,
+}
+
+/// Find the console of a ROM from the folders in its path, or its file name
+fn find_console(consoles_config: &ConsolesConfig, rom_path: &Path) -> Option<Console> {
+    rom_path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .find_map(|folder| consoles_config.find_console(&folder.as_os_str().to_string_lossy()))
+        .or_else(|| {
+            let file_name = rom_path.file_name()?.to_string_lossy();
+            consoles_config.find_console_by_file_name(&file_name)
+        })
+        .cloned()
+}
+
+pub async fn search_candidates(
+    State(state): State<AppState>,
+    Json(request): Json<CandidatesRequest>,
+) -> Result<Json<CandidatesResponse>, StatusCode> {
+    let roms_path = state.roms_path.lock().unwrap().clone();
+    let failure = |message: String| {
+        Ok(Json(CandidatesResponse {
+            success: false,
+            message,
+            results: Vec::new(),
+        }))
+    };
+
+    let consoles_config = match ConsolesConfig::load(&roms_path, state.consoles_path.as_deref()) {
+        Ok((consoles_config, _)) => consoles_config,
+        Err(e) => return failure(format!("Failed to load console config: {}", e)),
+    };
+
+    let rom_path = Path::new(&request.rom_path);
+    let Some(console) = find_console(&consoles_config, rom_path) else {
+        return failure(format!("No console found for {}", request.rom_path));
+    };
+    let name = match request.name {
+        Some(name) => name,
+        None => rom_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+
+    let scrapers = match build_metadata_scrapers(
+        &roms_path,
+        state.http_client.clone(),
+        request.metadata_backends,
+        &request.region_preferences,
+        &[],
+        Arc::new(HashIndex::open(&roms_path)),
+    )
+    .await
+    {
+        Ok(scrapers) => scrapers,
+        Err(e) => return failure(e.to_string()),
+    };
+
+    let mut results = Vec::new();
+    for scraper in &scrapers {
+        let (candidates, error) = match scraper.search_candidates(&name, &console).await {
+            Ok(candidates) => (candidates, None),
+            Err(e) => {
+                tracing::warn!("{} candidate search failed: {}", scraper.name(), e);
+                (Vec::new(), Some(e.to_string()))
+            }
+        };
+        results.push(BackendCandidates {
+            backend: scraper.name().to_string(),
+            candidates,
+            error,
+        });
+    }
+
+    Ok(Json(CandidatesResponse {
+        success: true,
+        message: format!("Searched {} for {}", console.name, name),
+        results,
+    }))
+}
+
+/// Pin a ROM to the candidate the user picked
+pub async fn choose_candidate(
+    state: State<AppState>,
+    Json(request): Json<ChooseCandidateRequest>,
+) -> Result<Json<OverridesResponse>, StatusCode> {
+    set_override(
+        state,
+        Json(SetOverrideRequest {
+            rom_path: request.rom_path,
+            backend: request.backend,
+            game_id: Some(request.game_id),
+        }),
+    )
+    .await
+}
@@ -0,0 +1,60 @@
+use axum::{Json, extract::State, http::StatusCode};
+use collie::scraper::screenscraper::{ScreenScraper, ScreenScraperConfig};
+use collie::scraper::{MetadataScraper, Quota, ScraperError};
+use serde::Serialize;
+
+use crate::server::state::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct CredentialsResponse {
+    pub success: bool,
+    pub message: String,
+    pub quota: Option<Quota>,
+}
+
+/// Check ScreenScraper credentials before a long run, reporting the account's quota
+pub async fn check_screenscraper(
+    State(state): State<AppState>,
+    Json(config): Json<ScreenScraperConfig>,
+) -> Result<Json<CredentialsResponse>, StatusCode> {
+    let mut scraper = ScreenScraper::new().with_http_client(state.http_client.clone());
+    if let (Some(username), Some(password)) = (config.username, config.password)
+        && let Err(e) = scraper.authenticate(&username, &password).await
+    {
+        return Ok(Json(CredentialsResponse {
+            success: false,
+            message: e.to_string(),
+            quota: None,
+        }));
+    }
+
+    let response = match scraper.check_credentials().await {
+        Ok(quota) => {
+            let mut message = format!(
+                "Logged in, {} of {} requests left today",
+                quota.remaining(),
+                quota.max_requests_per_day
+            );
+            if let Some(max_threads) = quota.max_threads {
+                message.push_str(&format!(", up to {} at once", max_threads));
+            }
+            CredentialsResponse {
+                success: true,
+                message,
+                quota: Some(quota),
+            }
+        }
+        Err(ScraperError::AuthenticationFailed) => CredentialsResponse {
+            success: false,
+            message: "Invalid ScreenScraper username or password".to_string(),
+            quota: None,
+        },
+        Err(e) => CredentialsResponse {
+            success: false,
+            message: format!("Failed to check credentials: {}", e),
+            quota: None,
+        },
+    };
+
+    Ok(Json(response))
+}
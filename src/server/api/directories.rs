@@ -86,7 +86,7 @@ pub async fn list_directories(
         .collect();
 
     // Sort alphabetically by name
-    directories.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    directories.sort_by_key(|a| a.name.to_lowercase());
 
     Ok(Json(ListDirectoriesResponse {
         current_path: dunce::simplified(&canonical_requested)
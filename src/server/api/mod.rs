@@ -1,4 +1,8 @@
+pub mod candidates;
+pub mod credentials;
 pub mod directories;
 pub mod games;
 pub mod images;
+pub mod overrides;
+pub mod rename;
 pub mod scrape;
@@ -0,0 +1,77 @@
+use axum::{Json, extract::State, http::StatusCode};
+use collie::overrides::Overrides;
+use serde::{Deserialize, Serialize};
+
+use crate::server::state::AppState;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetOverrideRequest {
+    /// ROM path relative to the ROMs folder, e.g. `GBA/Metroid Fusion (USA).gba`
+    pub rom_path: String,
+    /// Backend name, e.g. `screenscraper` or `thegamesdb`
+    pub backend: String,
+    /// Game ID to pin, or `null` to unpin
+    pub game_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OverridesResponse {
+    pub success: bool,
+    pub message: String,
+    pub overrides: Overrides,
+}
+
+pub async fn get_overrides(State(state): State<AppState>) -> Result<Json<Overrides>, StatusCode> {
+    let roms_path = state.roms_path.lock().unwrap().clone();
+
+    match Overrides::load(&roms_path) {
+        Ok(overrides) => Ok(Json(overrides)),
+        Err(e) => {
+            tracing::error!("Failed to load overrides: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn set_override(
+    State(state): State<AppState>,
+    Json(request): Json<SetOverrideRequest>,
+) -> Result<Json<OverridesResponse>, StatusCode> {
+    let roms_path = state.roms_path.lock().unwrap().clone();
+
+    let mut overrides = match Overrides::load(&roms_path) {
+        Ok(overrides) => overrides,
+        Err(e) => {
+            return Ok(Json(OverridesResponse {
+                success: false,
+                message: e.to_string(),
+                overrides: Overrides::default(),
+            }));
+        }
+    };
+
+    let rom_path = request.rom_path.replace('\\', "/");
+    let message = match &request.game_id {
+        Some(game_id) => format!(
+            "Pinned {} to {} game {}",
+            rom_path, request.backend, game_id
+        ),
+        None => format!("Unpinned {} from {}", rom_path, request.backend),
+    };
+    overrides.set(&rom_path, &request.backend, request.game_id);
+
+    if let Err(e) = overrides.save(&roms_path) {
+        return Ok(Json(OverridesResponse {
+            success: false,
+            message: format!("Failed to save overrides: {}", e),
+            overrides,
+        }));
+    }
+
+    Ok(Json(OverridesResponse {
+        success: true,
+        message,
+        overrides,
+    }))
+}
@@ -0,0 +1,138 @@
+use axum::{Json, extract::State, http::StatusCode};
+use collie::MediaLayout;
+use collie::hashing::HashIndex;
+use collie::rename::{DEFAULT_TEMPLATE, RenameConfig, RenameReport, rename_roms, undo_last_rename};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::server::state::AppState;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameRequest {
+    pub roms_path: String,
+    /// Naming template, e.g. "{title} ({region})"
+    #[serde(default = "default_template")]
+    pub template: String,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default = "default_max_scan_depth")]
+    pub max_scan_depth: usize,
+    #[serde(default)]
+    pub media_layout: MediaLayout,
+}
+
+fn default_template() -> String {
+    DEFAULT_TEMPLATE.to_string()
+}
+
+fn default_max_scan_depth() -> usize {
+    collie::scanner::DEFAULT_MAX_DEPTH
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenameResponse {
+    pub success: bool,
+    pub message: String,
+    pub report: Option<RenameReport>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoRenameRequest {
+    pub roms_path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UndoRenameResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+pub async fn rename(
+    State(state): State<AppState>,
+    Json(request): Json<RenameRequest>,
+) -> Result<Json<RenameResponse>, StatusCode> {
+    // Hold the lock so that scraping can't start while files are moving
+    let scraping = state.scraping.lock().await;
+    if *scraping {
+        return Ok(Json(RenameResponse {
+            success: false,
+            message: "Cannot rename while scraping is in progress".to_string(),
+            report: None,
+        }));
+    }
+
+    let roms_path = PathBuf::from(&request.roms_path);
+    let config = RenameConfig {
+        hash_index: Arc::new(HashIndex::open(&roms_path)),
+        roms_path,
+        consoles_path: state.consoles_path.clone(),
+        images_folder: "Imgs".to_string(),
+        guides_folder: "Guides".to_string(),
+        max_scan_depth: request.max_scan_depth,
+        media_layout: request.media_layout,
+        template: request.template,
+        dry_run: request.dry_run,
+    };
+
+    let response = match rename_roms(&config).await {
+        Ok(report) => RenameResponse {
+            success: true,
+            message: format!(
+                "{} {} games, skipped {}",
+                if report.dry_run {
+                    "Would rename"
+                } else {
+                    "Renamed"
+                },
+                report.renamed.len(),
+                report.skipped.len()
+            ),
+            report: Some(report),
+        },
+        Err(e) => {
+            tracing::error!("Renaming failed: {}", e);
+            RenameResponse {
+                success: false,
+                message: format!("Renaming failed: {}", e),
+                report: None,
+            }
+        }
+    };
+    drop(scraping);
+
+    Ok(Json(response))
+}
+
+pub async fn undo_rename(
+    State(state): State<AppState>,
+    Json(request): Json<UndoRenameRequest>,
+) -> Result<Json<UndoRenameResponse>, StatusCode> {
+    let scraping = state.scraping.lock().await;
+    if *scraping {
+        return Ok(Json(UndoRenameResponse {
+            success: false,
+            message: "Cannot undo a rename while scraping is in progress".to_string(),
+        }));
+    }
+
+    let response = match undo_last_rename(&PathBuf::from(&request.roms_path)) {
+        Ok(Some(steps)) => UndoRenameResponse {
+            success: true,
+            message: format!("Undid the last rename ({} steps)", steps),
+        },
+        Ok(None) => UndoRenameResponse {
+            success: false,
+            message: "Nothing to undo".to_string(),
+        },
+        Err(e) => UndoRenameResponse {
+            success: false,
+            message: format!("Undo failed: {}", e),
+        },
+    };
+    drop(scraping);
+
+    Ok(Json(response))
+}
@@ -4,12 +4,22 @@ use axum::{
     http::StatusCode,
     response::sse::{Event, Sse},
 };
+use collie::hashing::HashIndex;
+use collie::overrides::Overrides;
+use collie::scraper::http::HttpClient;
+use collie::scraper::media::MediaKind;
+use collie::scraper::preferences::RegionPreferences;
+use collie::scraper::screenscraper::ScreenScraper;
 use collie::scraper::screenscraper::ScreenScraperConfig;
+use collie::scraper::thegamesdb::TheGamesDB;
 use collie::scraper::thegamesdb::TheGamesDBConfig;
-use collie::{ProgressUpdate, scraper::gamefaqs::GameFAQsConfig};
+use collie::scraper::{MetadataScraper, ScraperError};
+use collie::{MediaLayout, ProgressUpdate, scraper::gamefaqs::GameFAQsConfig};
 use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
 use tokio_stream::StreamExt as _;
 use tokio_stream::wrappers::BroadcastStream;
 use tracing::debug;
@@ -33,10 +43,31 @@ pub struct ScrapeRequest {
     pub roms_path: String,
     pub box_art_width: u32,
     pub skip_cache: bool,
+    #[serde(default = "default_max_scan_depth")]
+    pub max_scan_depth: usize,
+    /// Identify loose ROMs in folders matching no console by file name or header
+    #[serde(default)]
+    pub detect_unknown_folders: bool,
+    #[serde(default)]
+    pub media_layout: MediaLayout,
+    #[serde(default)]
+    pub generate_m3u: bool,
+    #[serde(default)]
+    pub hide_discs: bool,
+    /// Region and language order for names, synopses, dates and media
+    #[serde(default)]
+    pub region_preferences: RegionPreferences,
+    /// Media to download besides the main image, e.g. `["ss", "video"]`
+    #[serde(default)]
+    pub media_kinds: Vec<MediaKind>,
     pub metadata_backends: MetadataBackendConfigs,
     pub guide_backends: GuideBackendConfigs,
 }
 
+fn default_max_scan_depth() -> usize {
+    collie::scanner::DEFAULT_MAX_DEPTH
+}
+
 #[derive(Debug, Serialize)]
 pub struct ScrapeResponse {
     pub success: bool,
@@ -286,24 +317,22 @@ pub async fn stop_scraping(
     }))
 }
 
-async fn run_scraping(
-    request: ScrapeRequest,
-    progress_json_tx: tokio::sync::broadcast::Sender<String>,
-    app_state: AppState,
-    cancel_token: tokio_util::sync::CancellationToken,
-) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    use collie::scraper::gamefaqs::GameFAQsScraper;
-    use collie::scraper::screenscraper::ScreenScraper;
-    use collie::scraper::thegamesdb::TheGamesDB;
-    use collie::scraper::{GuidesScraper, MetadataScraper};
-    use collie::{ScrapingConfig, scrape};
-
-    let roms_path = std::path::PathBuf::from(&request.roms_path);
-
+/// Create the enabled metadata scrapers, authenticating where credentials are given
+pub async fn build_metadata_scrapers(
+    roms_path: &Path,
+    http_client: Arc<HttpClient>,
+    backends: MetadataBackendConfigs,
+    region_preferences: &RegionPreferences,
+    media_kinds: &[MediaKind],
+    hash_index: Arc<HashIndex>,
+) -> Result<Vec<Box<dyn MetadataScraper>>, ScraperError> {
     let mut metadata_scrapers: Vec<Box<dyn MetadataScraper>> = Vec::new();
 
-    if let Some(ss_config) = request.metadata_backends.screenscraper {
-        let mut scraper = ScreenScraper::new().with_box_art_type(ss_config.box_art_type);
+    if let Some(ss_config) = backends.screenscraper {
+        let mut scraper = ScreenScraper::new()
+            .with_http_client(http_client.clone())
+            .with_hash_index(hash_index)
+            .with_preferences(region_preferences.clone());
 
         if let (Some(username), Some(password)) = (ss_config.username, ss_config.password) {
             scraper.authenticate(&username, &password).await?;
@@ -312,11 +341,49 @@ async fn run_scraping(
         metadata_scrapers.push(Box::new(scraper));
     }
 
-    if let Some(tgdb_config) = request.metadata_backends.thegamesdb {
-        let scraper = TheGamesDB::with_api_key(tgdb_config.api_key);
+    if let Some(tgdb_config) = backends.thegamesdb {
+        let scraper = TheGamesDB::with_api_key(tgdb_config.api_key)
+            .with_http_client(http_client)
+            .with_preferences(region_preferences.clone())
+            .with_media_kinds(media_kinds.to_vec())
+            .with_names_cache(roms_path.join(".collie").join("thegamesdb.json"));
         metadata_scrapers.push(Box::new(scraper));
     }
 
+    Ok(metadata_scrapers)
+}
+
+async fn run_scraping(
+    request: ScrapeRequest,
+    progress_json_tx: tokio::sync::broadcast::Sender<String>,
+    app_state: AppState,
+    cancel_token: tokio_util::sync::CancellationToken,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    use collie::scraper::GuidesScraper;
+    use collie::scraper::gamefaqs::GameFAQsScraper;
+    use collie::{ScrapingConfig, scrape};
+
+    let roms_path = std::path::PathBuf::from(&request.roms_path);
+    let consoles_path = app_state.consoles_path.clone();
+    let hash_index = Arc::new(HashIndex::open(&roms_path));
+    let overrides = Overrides::load(&roms_path).map_err(|e| e.to_string())?;
+
+    let box_art = request
+        .metadata_backends
+        .screenscraper
+        .as_ref()
+        .map(|ss_config| ss_config.box_art_type)
+        .unwrap_or_default();
+    let metadata_scrapers = build_metadata_scrapers(
+        &roms_path,
+        app_state.http_client.clone(),
+        request.metadata_backends,
+        &request.region_preferences,
+        &request.media_kinds,
+        hash_index.clone(),
+    )
+    .await?;
+
     let mut guides_scrapers: Vec<Box<dyn GuidesScraper>> = Vec::new();
 
     if let Some(_gamefaqs_config) = request.guide_backends.gamefaqs {
@@ -361,10 +428,20 @@ async fn run_scraping(
     let progress = tokio::spawn(async move {
         let config = ScrapingConfig {
             roms_path,
+            consoles_path,
             images_folder: "Imgs".to_string(),
             guides_folder: "Guides".to_string(),
             box_art_width: Some(request.box_art_width),
             skip_cache,
+            max_scan_depth: request.max_scan_depth,
+            detect_unknown_folders: request.detect_unknown_folders,
+            media_layout: request.media_layout,
+            generate_m3u: request.generate_m3u,
+            hide_discs: request.hide_discs,
+            hash_index,
+            overrides,
+            box_art,
+            media_kinds: request.media_kinds,
         };
 
         scrape(
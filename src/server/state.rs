@@ -1,3 +1,4 @@
+use collie::scraper::http::HttpClient;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::sync::Arc;
@@ -20,7 +21,10 @@ pub struct AppState {
     pub cancel_token: Arc<Mutex<Option<tokio_util::sync::CancellationToken>>>,
     pub progress_tx: broadcast::Sender<String>,
     pub roms_path: Arc<std::sync::Mutex<std::path::PathBuf>>,
+    pub consoles_path: Option<std::path::PathBuf>,
     pub scraping_state: Arc<Mutex<ScrapingState>>,
+    /// HTTP client shared by every scraper, so that rate limits hold across runs
+    pub http_client: Arc<HttpClient>,
 }
 
 /// Save scraping state to .collie/state.json
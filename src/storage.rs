@@ -1,18 +1,27 @@
 use crate::{progress::GameData, scanner};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-/// Load existing game data from .collie/games/<path>.json
-pub fn load_game_data(roms_path: &Path, rom: &scanner::RomFile) -> Option<GameData> {
-    let games_dir = roms_path.join(".collie").join("games");
-
-    // Create a path-safe filename using console and rom name
-    let safe_filename = format!(
+/// Create a path-safe filename using the console name and the ROM path within the console folder
+fn game_data_filename(rom: &scanner::RomFile) -> String {
+    let rom_path = rom.relative_dir().join(&rom.name_no_extension);
+    format!(
         "{}_{}.json",
         rom.console.name.replace(['/', '\\', ':'], "_"),
-        rom.name_no_extension.replace(['/', '\\', ':'], "_")
-    );
+        rom_path.to_string_lossy().replace(['/', '\\', ':'], "_")
+    )
+}
+
+/// Get the path of a ROM's game data, .collie/games/<path>.json
+pub fn game_data_path(roms_path: &Path, rom: &scanner::RomFile) -> PathBuf {
+    roms_path
+        .join(".collie")
+        .join("games")
+        .join(game_data_filename(rom))
+}
 
-    let game_file = games_dir.join(safe_filename);
+/// Load existing game data from .collie/games/<path>.json
+pub fn load_game_data(roms_path: &Path, rom: &scanner::RomFile) -> Option<GameData> {
+    let game_file = game_data_path(roms_path, rom);
 
     if !game_file.exists() {
         return None;
@@ -33,14 +42,7 @@ pub fn save_game_data(
     let games_dir = roms_path.join(".collie").join("games");
     std::fs::create_dir_all(&games_dir)?;
 
-    // Create a path-safe filename using console and rom name
-    let safe_filename = format!(
-        "{}_{}.json",
-        rom.console.name.replace(['/', '\\', ':'], "_"),
-        rom.name_no_extension.replace(['/', '\\', ':'], "_")
-    );
-
-    let game_file = games_dir.join(safe_filename);
+    let game_file = games_dir.join(game_data_filename(rom));
     let json = serde_json::to_string_pretty(game_data)?;
     std::fs::write(&game_file, json)?;
 
@@ -57,8 +59,8 @@ pub fn append_crawled_path(
 
     let crawled_file = collie_dir.join("crawled");
 
-    // Create a path entry with console and rom name using PathBuf to ensure correct separator
-    let path = std::path::PathBuf::from(&rom.console.name).join(&rom.name);
+    // Create a path entry with console and rom path using PathBuf to ensure correct separator
+    let path = std::path::PathBuf::from(&rom.console.name).join(&rom.relative_path);
     let path_entry = format!("{}\n", path.display());
 
     // Append to file
@@ -113,13 +115,25 @@ mod tests {
             screenscraper_id: None,
             thegamesdb_id: None,
             gamefaqs_archive_id: None,
+            extensions: vec![],
+            cores: vec![],
+            file_name: vec![],
+            headers: vec![],
+            arcade: false,
         };
 
         let rom = scanner::RomFile {
             path: PathBuf::from("GBA/game.gba"),
+            relative_path: PathBuf::from("game.gba"),
             name: "game.gba".to_string(),
             name_no_extension: "game".to_string(),
             console,
+            discs: vec![],
+            companions: vec![],
+            serial: None,
+            parsed_name: Default::default(),
+            dat_match: None,
+            arcade_set: None,
         };
 
         append_crawled_path(&temp_dir, &rom).unwrap();
@@ -159,13 +173,25 @@ mod tests {
             screenscraper_id: None,
             thegamesdb_id: None,
             gamefaqs_archive_id: None,
+            extensions: vec![],
+            cores: vec![],
+            file_name: vec![],
+            headers: vec![],
+            arcade: false,
         };
 
         let rom = scanner::RomFile {
             path: PathBuf::from("PS/test.bin"),
+            relative_path: PathBuf::from("test.bin"),
             name: "test.bin".to_string(),
             name_no_extension: "test".to_string(),
             console,
+            discs: vec![],
+            companions: vec![],
+            serial: None,
+            parsed_name: Default::default(),
+            dat_match: None,
+            arcade_set: None,
         };
 
         let game_data = GameData {
@@ -180,6 +206,8 @@ mod tests {
                 rating: None,
                 image_path: None,
                 error_message: None,
+                confidence: None,
+                pinned_ids: Default::default(),
             },
             guides: GameGuides {
                 status: ScrapeStatus::Pending,
@@ -210,14 +238,26 @@ mod tests {
             screenscraper_id: None,
             thegamesdb_id: None,
             gamefaqs_archive_id: None,
+            extensions: vec![],
+            cores: vec![],
+            file_name: vec![],
+            headers: vec![],
+            arcade: false,
         };
 
         // Test with special characters that should be replaced
         let rom = scanner::RomFile {
             path: PathBuf::from("N64/test:game/special.z64"),
+            relative_path: PathBuf::from("test:game/special.z64"),
             name: "test:game/special.z64".to_string(),
             name_no_extension: "test:game/special".to_string(),
             console,
+            discs: vec![],
+            companions: vec![],
+            serial: None,
+            parsed_name: Default::default(),
+            dat_match: None,
+            arcade_set: None,
         };
 
         let game_data = GameData {
@@ -232,6 +272,8 @@ mod tests {
                 rating: None,
                 image_path: None,
                 error_message: None,
+                confidence: None,
+                pinned_ids: Default::default(),
             },
             guides: GameGuides {
                 status: ScrapeStatus::Pending,
@@ -249,4 +291,50 @@ mod tests {
         // Clean up
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
+
+    #[test]
+    fn test_nested_roms_get_distinct_game_data() {
+        let console = Console {
+            name: "GBA".to_string(),
+            patterns: vec!["gba".to_string()],
+            screenscraper_id: None,
+            thegamesdb_id: None,
+            gamefaqs_archive_id: None,
+            extensions: vec![],
+            cores: vec![],
+            file_name: vec![],
+            headers: vec![],
+            arcade: false,
+        };
+
+        let top_level = scanner::RomFile {
+            path: PathBuf::from("GBA/game.gba"),
+            relative_path: PathBuf::from("game.gba"),
+            name: "game.gba".to_string(),
+            name_no_extension: "game".to_string(),
+            console: console.clone(),
+            discs: vec![],
+            companions: vec![],
+            serial: None,
+            parsed_name: Default::default(),
+            dat_match: None,
+            arcade_set: None,
+        };
+        let nested = scanner::RomFile {
+            path: PathBuf::from("GBA/RPG/game.gba"),
+            relative_path: PathBuf::from("RPG").join("game.gba"),
+            name: "game.gba".to_string(),
+            name_no_extension: "game".to_string(),
+            console,
+            discs: vec![],
+            companions: vec![],
+            serial: None,
+            parsed_name: Default::default(),
+            dat_match: None,
+            arcade_set: None,
+        };
+
+        assert_eq!(game_data_filename(&top_level), "GBA_game.json");
+        assert_eq!(game_data_filename(&nested), "GBA_RPG_game.json");
+    }
 }
@@ -0,0 +1,383 @@
+//! Scrapes `tests/Roms` end to end against local stand-ins for ScreenScraper, TheGamesDB and
+//! the GameFAQs gopher archive, which serve the recorded responses in `tests/fixtures`.
+
+use axum::Router;
+use axum::extract::{Path as UrlPath, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use collie::hashing::HashIndex;
+use collie::overrides::Overrides;
+use collie::scraper::gamefaqs::GameFAQsScraper;
+use collie::scraper::http::{HttpClient, HttpConfig};
+use collie::scraper::media::MediaKind;
+use collie::scraper::screenscraper::ScreenScraper;
+use collie::scraper::thegamesdb::TheGamesDB;
+use collie::scraper::{GuidesScraper, MetadataScraper};
+use collie::{GameData, MediaLayout, ScrapeStatus, ScrapingConfig, ScrapingProgress, scrape};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+}
+
+/// Requests received by the HTTP stand-in, and the URL it is served at
+struct Recorder {
+    base_url: String,
+    requests: Mutex<Vec<String>>,
+}
+
+impl Recorder {
+    /// Read a recorded response, pointing its media URLs at the stand-in
+    fn fixture(&self, path: PathBuf) -> Option<String> {
+        let body = std::fs::read_to_string(path).ok()?;
+        Some(body.replace("{{base_url}}", &self.base_url))
+    }
+
+    fn log(&self, request: String) {
+        self.requests.lock().unwrap().push(request);
+    }
+
+    fn count(&self, request: &str) -> usize {
+        let requests = self.requests.lock().unwrap();
+        requests.iter().filter(|r| *r == request).count()
+    }
+}
+
+fn json(body: String) -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+async fn screenscraper(
+    State(recorder): State<Arc<Recorder>>,
+    UrlPath(endpoint): UrlPath<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    match params.get("gameid") {
+        Some(game_id) => recorder.log(format!("screenscraper/{}?gameid={}", endpoint, game_id)),
+        None => recorder.log(format!("screenscraper/{}", endpoint)),
+    }
+    let dir = fixtures_dir().join("screenscraper");
+
+    match endpoint.as_str() {
+        // Looked up by ROM name, or by game ID for pinned ROMs
+        "jeuInfos.php" => params
+            .get("romnom")
+            .or_else(|| params.get("gameid"))
+            .and_then(|name| recorder.fixture(dir.join("jeuInfos").join(format!("{name}.json"))))
+            .map(json)
+            .unwrap_or_else(|| {
+                (
+                    StatusCode::NOT_FOUND,
+                    "Erreur : Rom/Iso/Dossier non trouvée !",
+                )
+                    .into_response()
+            }),
+        // Searches without results return a single empty game
+        "jeuRecherche.php" => json(
+            params
+                .get("recherche")
+                .and_then(|title| {
+                    recorder.fixture(dir.join("jeuRecherche").join(format!("{title}.json")))
+                })
+                .unwrap_or_else(|| r#"{"response": {"jeux": [{}]}}"#.to_string()),
+        ),
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn thegamesdb(
+    State(recorder): State<Arc<Recorder>>,
+    UrlPath(endpoint): UrlPath<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    recorder.log(format!("thegamesdb/{}", endpoint));
+    if params.get("apikey").map(String::as_str) != Some("test-key") {
+        return (StatusCode::FORBIDDEN, "Invalid API key").into_response();
+    }
+    let dir = fixtures_dir().join("thegamesdb");
+
+    match endpoint.as_str() {
+        "Games/ByGameName" => json(
+            params
+                .get("name")
+                .and_then(|name| {
+                    recorder.fixture(dir.join("ByGameName").join(format!("{name}.json")))
+                })
+                .unwrap_or_else(|| {
+                    r#"{"code": 200, "status": "Success", "data": {"count": 0, "games": []}}"#
+                        .to_string()
+                }),
+        ),
+        "Games/ByGameID" => params
+            .get("id")
+            .and_then(|id| recorder.fixture(dir.join("ByGameID").join(format!("{id}.json"))))
+            .map(json)
+            .unwrap_or_else(|| StatusCode::NOT_FOUND.into_response()),
+        "Developers" | "Publishers" | "Genres" => recorder
+            .fixture(dir.join(format!("{endpoint}.json")))
+            .map(json)
+            .unwrap_or_else(|| StatusCode::NOT_FOUND.into_response()),
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Serve the same small PNG for every media URL
+async fn media(State(recorder): State<Arc<Recorder>>, UrlPath(path): UrlPath<String>) -> Response {
+    recorder.log(format!("medias/{}", path));
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::RgbImage::from_pixel(4, 6, image::Rgb([200, 40, 40]))
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+    ([(header::CONTENT_TYPE, "image/png")], png.into_inner()).into_response()
+}
+
+/// Start the ScreenScraper, TheGamesDB and media stand-in on a free port
+async fn start_http_server() -> Arc<Recorder> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let recorder = Arc::new(Recorder {
+        base_url: format!("http://{}", listener.local_addr().unwrap()),
+        requests: Mutex::new(Vec::new()),
+    });
+
+    let app = Router::new()
+        .route("/screenscraper/{endpoint}", get(screenscraper))
+        .route("/thegamesdb/{*endpoint}", get(thegamesdb))
+        .route("/medias/{*path}", get(media))
+        .with_state(recorder.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    recorder
+}
+
+/// Start the gopher stand-in on a free port, serving `gophermap` files for folders
+fn start_gopher_server() -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let root = fixtures_dir().join("gamefaqs");
+
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut selector = String::new();
+            if BufReader::new(&stream).read_line(&mut selector).is_err() {
+                continue;
+            }
+
+            let path = root.join(selector.trim().trim_start_matches('/'));
+            let path = if path.is_dir() {
+                path.join("gophermap")
+            } else {
+                path
+            };
+            let response = std::fs::read_to_string(&path)
+                .unwrap_or_else(|_| "3Not found\terror\terror.host\t1\r\n.\r\n".to_string());
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    port
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    std::fs::create_dir_all(to).unwrap();
+    for entry in std::fs::read_dir(from).unwrap().flatten() {
+        let target = to.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &target);
+        } else {
+            std::fs::copy(entry.path(), target).unwrap();
+        }
+    }
+}
+
+/// Load the stored game data of every scraped ROM by file name
+fn load_games(roms_path: &Path) -> HashMap<String, GameData> {
+    std::fs::read_dir(roms_path.join(".collie").join("games"))
+        .unwrap()
+        .flatten()
+        .map(|entry| {
+            let game: GameData =
+                serde_json::from_str(&std::fs::read_to_string(entry.path()).unwrap()).unwrap();
+            (game.rom_name.clone(), game)
+        })
+        .collect()
+}
+
+/// Scrape the ROMs once with fresh scrapers pointed at the stand-ins
+async fn scrape_once(
+    recorder: &Recorder,
+    gopher_port: u16,
+    roms_path: &Path,
+    overrides: Overrides,
+) -> ScrapingProgress {
+    // One client shared by both backends, without a rate limit to keep the test fast
+    let http_client = Arc::new(
+        HttpClient::new(HttpConfig {
+            requests_per_second: 0.0,
+            ..Default::default()
+        })
+        .unwrap(),
+    );
+    let mut screenscraper = ScreenScraper::new()
+        .with_http_client(http_client.clone())
+        .with_base_url(format!("{}/screenscraper", recorder.base_url));
+    screenscraper
+        .authenticate("collie", "hunter2")
+        .await
+        .unwrap();
+    let thegamesdb = TheGamesDB::with_api_key("test-key".to_string())
+        .with_http_client(http_client)
+        .with_base_url(format!("{}/thegamesdb", recorder.base_url))
+        .with_names_cache(roms_path.join(".collie").join("thegamesdb.json"));
+    let metadata_scrapers: Vec<Box<dyn MetadataScraper>> =
+        vec![Box::new(screenscraper), Box::new(thegamesdb)];
+    let guides_scrapers: Vec<Box<dyn GuidesScraper>> = vec![Box::new(
+        GameFAQsScraper::new().with_host("127.0.0.1", gopher_port),
+    )];
+
+    let config = ScrapingConfig {
+        roms_path: roms_path.to_path_buf(),
+        consoles_path: None,
+        images_folder: "Imgs".to_string(),
+        guides_folder: "Guides".to_string(),
+        box_art_width: None,
+        skip_cache: false,
+        max_scan_depth: collie::scanner::DEFAULT_MAX_DEPTH,
+        detect_unknown_folders: false,
+        media_layout: MediaLayout::Mirrored,
+        generate_m3u: false,
+        hide_discs: false,
+        hash_index: Arc::new(HashIndex::open(roms_path)),
+        overrides,
+        box_art: MediaKind::BoxFront,
+        media_kinds: vec![MediaKind::Screenshot],
+    };
+
+    let (progress_tx, _progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    scrape(
+        metadata_scrapers,
+        guides_scrapers,
+        config,
+        tokio_util::sync::CancellationToken::new(),
+        progress_tx,
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scrape_against_stand_ins() {
+    let temp_dir = std::env::temp_dir().join("collie_test_scrape_stand_ins");
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    let roms_path = temp_dir.join("Roms");
+    copy_dir(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("Roms"),
+        &roms_path,
+    );
+
+    let recorder = start_http_server().await;
+    let gopher_port = start_gopher_server();
+
+    let progress = scrape_once(&recorder, gopher_port, &roms_path, Overrides::default()).await;
+
+    let games = load_games(&roms_path);
+    let status = |rom_name: &str| games[rom_name].metadata.status;
+    assert_eq!(progress.total, games.len());
+
+    // Matched by checksum lookup on ScreenScraper, with a screenshot and a guide
+    let metroid = &games["Metroid Fusion (USA).gba"].metadata;
+    assert_eq!(metroid.status, ScrapeStatus::Success);
+    assert_eq!(metroid.developer.as_deref(), Some("Nintendo R&D1"));
+    assert!(image::open(roms_path.join("GBA/Imgs/Metroid Fusion (USA).png")).is_ok());
+    assert!(
+        roms_path
+            .join("GBA/Imgs/screenshots/Metroid Fusion (USA).png")
+            .exists()
+    );
+    let guide = std::fs::read_to_string(
+        roms_path.join("GBA/Guides/Metroid Fusion (USA)/FAQ_Walkthrough-by--Samus.txt"),
+    )
+    .unwrap();
+    assert!(guide.starts_with("METROID FUSION"));
+
+    // Unknown checksums fall back to a ScreenScraper title search
+    let zelda = &games["The Legend of Zelda - The Minish Cap (USA).gba"].metadata;
+    assert_eq!(zelda.status, ScrapeStatus::Success);
+    assert_eq!(zelda.developer.as_deref(), Some("Capcom"));
+    assert!(zelda.confidence.unwrap() >= collie::scraper::ranking::MIN_CONFIDENCE);
+
+    // Missing from ScreenScraper, found on TheGamesDB with resolved names
+    let pokemon = &games["Pokemon - Emerald Version (USA, Europe).gba"].metadata;
+    assert_eq!(pokemon.status, ScrapeStatus::Success);
+    assert_eq!(pokemon.name.as_deref(), Some("Pokemon Emerald Version"));
+    assert_eq!(pokemon.developer.as_deref(), Some("Game Freak"));
+    assert_eq!(pokemon.publisher.as_deref(), Some("Nintendo"));
+    assert_eq!(pokemon.genre.as_deref(), Some("Role-Playing"));
+    assert!(
+        image::open(roms_path.join("GBA/Imgs/Pokemon - Emerald Version (USA, Europe).png")).is_ok()
+    );
+
+    let crash = &games["Crash Bandicoot (USA).bin"].metadata;
+    assert_eq!(crash.status, ScrapeStatus::Success);
+    assert_eq!(crash.developer.as_deref(), Some("Naughty Dog"));
+
+    // Found nowhere
+    assert_eq!(
+        status("Metal Gear Solid (USA) (Rev 1).bin"),
+        ScrapeStatus::Failed
+    );
+    assert_eq!(progress.success_count, 4);
+    assert_eq!(progress.fail_count, progress.total - 4);
+
+    // Name lists are fetched once and cached
+    assert_eq!(recorder.count("thegamesdb/Developers"), 1);
+    assert!(roms_path.join(".collie").join("thegamesdb.json").exists());
+
+    // ScreenScraper's quota is tracked from its responses
+    let quota = progress.quotas["ScreenScraper"];
+    assert_eq!(quota.max_requests_per_day, 20000);
+    assert_eq!(quota.max_threads, Some(1));
+    assert!(quota.requests_today >= 41);
+
+    // Pinned ROMs are scraped again when their pins change, and cached after that
+    let mut overrides = Overrides::default();
+    overrides.set(
+        "GBA/Metroid Fusion (USA).gba",
+        "ScreenScraper",
+        Some("3411".to_string()),
+    );
+    overrides.set(
+        "GBA/The Legend of Zelda - The Minish Cap (USA).gba",
+        "TheGamesDB",
+        Some("1729".to_string()),
+    );
+    for _ in 0..2 {
+        scrape_once(&recorder, gopher_port, &roms_path, overrides.clone()).await;
+        assert_eq!(recorder.count("screenscraper/jeuInfos.php?gameid=3411"), 1);
+        assert_eq!(recorder.count("thegamesdb/Games/ByGameID"), 1);
+    }
+    let games = load_games(&roms_path);
+    let metroid = &games["Metroid Fusion (USA).gba"].metadata;
+    assert_eq!(metroid.status, ScrapeStatus::Skipped);
+    assert_eq!(metroid.pinned_ids["screenscraper"], "3411");
+
+    // A pin on a later backend wins over an automatic match on ScreenScraper
+    let zelda = &games["The Legend of Zelda - The Minish Cap (USA).gba"].metadata;
+    assert_eq!(
+        zelda.name.as_deref(),
+        Some("The Legend of Zelda: The Minish Cap")
+    );
+    assert_eq!(zelda.publisher.as_deref(), Some("Nintendo"));
+    assert_eq!(zelda.developer, None);
+
+    // Clean up
+    let _ = std::fs::remove_dir_all(&temp_dir);
+}